@@ -1,20 +1,33 @@
 #![allow(clippy::collapsible_else_if)]
 use std::io::{Read, Write};
 use std::path::{PathBuf, Path};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use calmare::parse::diag::Level;
+use gospel::read::Reader;
 use clap::{Parser, ValueHint};
+use eyre::WrapErr;
 use themelios::lookup::Lookup;
 use themelios::types::Game;
 use themelios::scena::ed6::Scena as ED6Scena;
 use themelios::scena::ed7::Scena as ED7Scena;
+use themelios::scena::detect;
+use themelios::identify::Table;
 
 #[derive(Debug, Clone, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+	#[command(subcommand)]
+	command: Option<Command>,
+
 	/// Where to place the output.
 	///
 	/// If unspecified, output will be placed next to the input file.
-	#[clap(long, short, value_hint = ValueHint::FilePath)]
+	///
+	/// When processing multiple files or a directory, this is a directory, and the output is placed
+	/// in a tree mirroring the input.
+	#[clap(long, short, value_hint = ValueHint::AnyPath)]
 	output: Option<PathBuf>,
 
 	/// Force compile mode.
@@ -27,41 +40,70 @@ struct Cli {
 	#[clap(long, short, conflicts_with = "compile")]
 	decompile: bool,
 
+	/// Instead of writing any output, check that the files are unchanged after decompiling and
+	/// recompiling them.
+	#[clap(long, conflicts_with_all = ["compile", "decompile", "output"])]
+	check: bool,
+
 	/// Game to decompile as.
 	///
 	/// There is no indicator in the binary files which game it belongs to, so unless specified,
-	/// this will be determined heuristically, which may lead to incorrect decompilation.
+	/// this will be guessed from the file's contents and name. A warning is printed if the guess is
+	/// ambiguous.
+	///
+	/// Required for tables, such as t_name._dt, and animation scripts, since these are named the
+	/// same in all games.
 	///
 	/// Has no effect for compilation.
 	#[clap(long, short, hide_possible_values = true)]
-	game: Option<CliGame>,
+	game: Option<Game>,
+
+	/// File name index to use, for example if mods have added new files.
+	///
+	/// Can be a game directory, either the root directory of a PC game or the data directory of an
+	/// Evolution game, or an index file as written by `factoria make-index`.
+	///
+	/// Can be given more than once, in which case the later ones are layered on top of the first,
+	/// such as `--lookup game --lookup mod.txt` for a text file listing the files a mod adds.
+	///
+	/// If unspecified, Calmare's built-in indexes are used. Only meaningful for the Sky games.
+	#[clap(long, short, value_hint = ValueHint::AnyPath)]
+	lookup: Vec<PathBuf>,
+
+	/// Number of files to process in parallel, when processing multiple files.
+	///
+	/// Defaults to the number of CPUs.
+	#[clap(long, short)]
+	jobs: Option<usize>,
 
-	/// The file to process.
+	/// The files to process.
+	///
+	/// Can be `-` to read from stdin. Directories are searched recursively for .clm files to
+	/// compile and ._sn, .bin, table, and animation script files to decompile.
 	///
-	/// Can be `-` to read from stdin.
-	#[clap(required = true, value_hint = ValueHint::FilePath)]
-	file: PathBuf,
+	/// When decompiling a scena, its functions are named according to the .sym file next to it, if
+	/// any. This file has lines like `fn[3] talk_to_mayor`.
+	#[clap(required = true, value_hint = ValueHint::AnyPath)]
+	files: Vec<PathBuf>,
 }
 
-// Feels like I'm implementing this mapping way too often. Gotta do something about that.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
-enum CliGame {
-	#[value(name = "fc")] Fc,
-	#[value(name = "fc_e")] FcEvo,
-	#[value(name = "fc_k")] FcKai,
-	#[value(name = "sc")] Sc,
-	#[value(name = "sc_e")] ScEvo,
-	#[value(name = "sc_k")] ScKai,
-	#[value(name = "tc")] Tc,
-	#[value(name = "tc_e")] TcEvo,
-	#[value(name = "tc_k")] TcKai,
-
-	#[value(name = "zero")] Zero,
-	#[value(name = "zero_e")] ZeroEvo,
-	#[value(name = "zero_k")] ZeroKai,
-	#[value(name = "ao")] Ao,
-	#[value(name = "ao_e")] AoEvo,
-	#[value(name = "ao_k")] AoKai,
+#[derive(Debug, Clone, clap::Subcommand)]
+enum Command {
+	/// Normalize the formatting of .clm files, keeping comments.
+	///
+	/// Files are rewritten in place.
+	Fmt {
+		/// Instead of writing any output, list the files that are not formatted, and fail if there
+		/// are any.
+		#[clap(long)]
+		check: bool,
+
+		/// The files to format.
+		///
+		/// Can be `-` to format stdin to stdout. Directories are searched recursively for .clm files.
+		#[clap(required = true, value_hint = ValueHint::AnyPath)]
+		files: Vec<PathBuf>,
+	},
 }
 
 #[cfg(target_os = "windows")]
@@ -95,8 +137,38 @@ fn main_inner() -> eyre::Result<()> {
 			std::process::exit(2);
 		},
 	};
+	if let Some(Command::Fmt { check, files }) = &cli.command {
+		return fmt(&cli, files, *check)
+	}
+
+	let lookup = cli.lookup.split_first().map(|(base, layers)| {
+		themelios::lookup::load_layered(base, layers).wrap_err("failed to load lookup")
+	}).transpose()?;
+	let lookup = lookup.as_ref().map(|a| a as &(dyn Lookup + Sync));
+
+	match cli.files.as_slice() {
+		[file] if !file.is_dir() => {
+			let diags = process(&cli, file, file, cli.output.as_deref(), lookup)?;
+			if diags > 0 {
+				windows_wait();
+			}
+			Ok(())
+		}
+		_ => process_batch(&cli, lookup),
+	}
+}
+
+/// Compiles or decompiles a single file, returning the number of diagnostics.
+///
+/// The output is written to `output` if given, otherwise to `out_base` with the extension replaced.
+fn process(cli: &Cli, input: &Path, out_base: &Path, output: Option<&Path>, lookup: Option<&(dyn Lookup + Sync)>) -> eyre::Result<usize> {
+	let lookup = lookup.map(|a| a as &dyn Lookup);
+	if cli.check {
+		return check(cli, input, lookup)
+	}
+
 	let mut buf = Vec::new();
-	get_input(&cli.file)?.read_to_end(&mut buf)?;
+	get_input(input)?.read_to_end(&mut buf)?;
 
 	let src = if cli.decompile {
 		None
@@ -106,101 +178,319 @@ fn main_inner() -> eyre::Result<()> {
 		is_text.then_some(v)
 	};
 
-	let lookup = None;
-
 	if let Some(src) = src {
 		let src = src?;
-		let (val, diags) = calmare::parse(src, lookup);
-		let filename = if cli.file.as_os_str() == "-" {
+		// Files in `use` are relative to the file being compiled
+		let dir = input.parent().unwrap_or(Path::new(""));
+		let (val, diags) = calmare::parse_with(src, lookup, &|path| std::fs::read_to_string(dir.join(path)));
+		let filename = if input.as_os_str() == "-" {
 			"<stdin>".into()
 		} else {
-			cli.file.as_os_str().to_string_lossy()
+			input.as_os_str().to_string_lossy()
 		};
 		print_diags(&filename, src, &diags);
 		let Some((game, val)) = val else {
 			eyre::bail!("failed with {} errors", diags.iter().filter(|a| a.is_fatal()).count())
 		};
 
-		match val {
-			calmare::Content::ED6Scena(s) => {
-				let suffix = if matches!(game, Game::Fc|Game::Sc|Game::Tc) {
-					"_sn"
-				} else {
-					"bin"
+		let suffix = match val {
+			calmare::Content::ED6Scena(_) if matches!(game, Game::Fc|Game::Sc|Game::Tc) => "_sn",
+			calmare::Content::ED6Scena(_) | calmare::Content::ED7Scena(_) => "bin",
+			calmare::Content::ED6Ent(_) => "_en",
+			_ => "_dt",
+		};
+		let data = write_content(game, &val)?;
+		get_output(output, out_base, suffix)?
+			.write_all(&data)?;
+
+		Ok(diags.len())
+	} else {
+		let (game, c) = read_content(cli.game, input, &buf)?;
+		let src = calmare::to_string_with_names(game, &c, lookup, &read_symbols(input)?);
+		get_output(output, out_base, "clm")?
+			.write_all(src.as_bytes())?;
+		Ok(0)
+	}
+}
+
+fn process_batch(cli: &Cli, lookup: Option<&(dyn Lookup + Sync)>) -> eyre::Result<()> {
+	// Pairs of input file and where to place the output, sans extension
+	let mut jobs = Vec::new();
+	for file in &cli.files {
+		if file.is_dir() {
+			let mut files = Vec::new();
+			find_files(cli, file, &mut files)?;
+			for f in files {
+				let out = match &cli.output {
+					Some(o) => o.join(f.strip_prefix(file)?),
+					None => f.clone(),
 				};
-				let data = ED6Scena::write(game, &s)?;
-				get_output(cli.output.as_deref(), &cli.file, suffix)?
-					.write_all(&data)?;
-			}
-			calmare::Content::ED7Scena(s) => {
-				let data = ED7Scena::write(game, &s)?;
-				get_output(cli.output.as_deref(), &cli.file, "bin")?
-					.write_all(&data)?;
+				jobs.push((f, out));
 			}
+		} else {
+			let out = match (&cli.output, file.file_name()) {
+				(Some(o), Some(name)) => o.join(name),
+				_ => file.clone(),
+			};
+			jobs.push((file.clone(), out));
 		}
+	}
 
-		if !diags.is_empty() {
-			windows_wait();
+	// Files with the same name but different extensions, like t0100.clm and t0100._sn, would write
+	// each other's inputs, so leave them for the user to sort out
+	let mut names = std::collections::HashMap::<PathBuf, Vec<PathBuf>>::new();
+	for (input, out) in &jobs {
+		names.entry(out.with_extension("")).or_default().push(input.clone());
+	}
+	let conflicts = jobs.iter()
+		.filter_map(|(input, out)| {
+			let same = &names[&out.with_extension("")];
+			(same.len() > 1).then(|| (input.clone(), same.iter().filter(|a| *a != input).map(|a| a.display().to_string()).collect::<Vec<_>>()))
+		})
+		.collect::<Vec<_>>();
+	jobs.retain(|(_, out)| names[&out.with_extension("")].len() == 1);
+
+	let threads = cli.jobs
+		.or_else(|| std::thread::available_parallelism().ok().map(|a| a.get()))
+		.unwrap_or(1)
+		.clamp(1, jobs.len().max(1));
+	let next = AtomicUsize::new(0);
+	let results = Mutex::new((0..jobs.len()).map(|_| None).collect::<Vec<_>>());
+	std::thread::scope(|s| {
+		for _ in 0..threads {
+			s.spawn(|| loop {
+				let i = next.fetch_add(1, Ordering::Relaxed);
+				let Some((input, out)) = jobs.get(i) else { break };
+				let result = out.parent()
+					.map_or(Ok(()), std::fs::create_dir_all)
+					.map_err(Into::into)
+					.and_then(|()| process(cli, input, out, None, lookup));
+				results.lock().unwrap()[i] = Some(result);
+			});
 		}
+	});
+
+	for (input, others) in &conflicts {
+		println!("skipped {}: conflicts with {}", input.display(), others.join(", "));
+	}
+	let mut failed = 0;
+	for ((input, _), result) in jobs.iter().zip(results.into_inner().unwrap()) {
+		match result.unwrap() {
+			Ok(0) => println!("ok      {}", input.display()),
+			Ok(n) => println!("ok      {} ({n} diagnostics)", input.display()),
+			Err(e) => {
+				println!("failed  {}: {e}", input.display());
+				failed += 1;
+			}
+		}
+	}
+	if conflicts.is_empty() {
+		println!("{} succeeded, {failed} failed", jobs.len() - failed);
 	} else {
-		let src = write_scena(cli.game, &buf, lookup)?;
-		get_output(cli.output.as_deref(), &cli.file, "clm")?
-			.write_all(src.as_bytes())?;
+		println!("{} succeeded, {failed} failed, {} skipped", jobs.len() - failed, conflicts.len());
 	}
+	eyre::ensure!(failed == 0, "{failed} files failed");
+	Ok(())
+}
 
+/// Formats each file in place, or with `check`, only reports the ones that would change.
+fn fmt(cli: &Cli, files: &[PathBuf], check: bool) -> eyre::Result<()> {
+	let mut paths = Vec::new();
+	for file in files {
+		if file.is_dir() {
+			let mut found = Vec::new();
+			find_files(cli, file, &mut found)?;
+			paths.extend(found.into_iter().filter(|a| a.extension().is_some_and(|a| a == "clm")));
+		} else {
+			paths.push(file.clone());
+		}
+	}
+
+	let mut changed = 0;
+	for path in &paths {
+		let mut src = String::new();
+		get_input(path)?.read_to_string(&mut src)?;
+		let (out, diags) = calmare::parse::fmt::format(&src);
+		let is_stdin = path.as_os_str() == "-";
+		let filename = if is_stdin { "<stdin>".into() } else { path.as_os_str().to_string_lossy() };
+		print_diags(&filename, &src, &diags);
+		let Some(out) = out else {
+			eyre::bail!("failed to format {filename}")
+		};
+		if out == src {
+			if is_stdin && !check {
+				std::io::stdout().write_all(out.as_bytes())?;
+			}
+			continue
+		}
+		changed += 1;
+		if check {
+			println!("unformatted  {filename}");
+		} else if is_stdin {
+			std::io::stdout().write_all(out.as_bytes())?;
+		} else {
+			std::fs::write(path, out)?;
+		}
+	}
+	eyre::ensure!(!check || changed == 0, "{changed} files are not formatted");
 	Ok(())
 }
 
-fn write_scena(game: Option<CliGame>, buf: &[u8], lookup: Option<&dyn Lookup>) -> eyre::Result<String> {
-	match game {
-		Some(game) => {
-			let game = cli_game(game);
-			let c = if game.is_ed7() {
-				calmare::Content::ED7Scena(ED7Scena::read(game, buf)?)
-			} else {
-				calmare::Content::ED6Scena(ED6Scena::read(game, buf)?)
-			};
-			Ok(calmare::to_string(game, &c, lookup))
-		},
+fn find_files(cli: &Cli, dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+	let mut entries = dir.read_dir()?.collect::<Result<Vec<_>, _>>()?;
+	entries.sort_by_key(|a| a.path());
+	for e in entries {
+		let path = e.path();
+		if e.file_type()?.is_dir() {
+			find_files(cli, &path, out)?;
+			continue
+		}
+		let is_src = path.extension().is_some_and(|a| a == "clm");
+		let name = path.file_name().and_then(|a| a.to_str()).unwrap_or_default();
+		let is_bin = path.extension().is_some_and(|a| a == "_sn" || a == "bin")
+			|| Table::from_name(name).is_some() || is_ani(name);
+		if is_src && !cli.decompile && !cli.check || is_bin && !cli.compile {
+			out.push(path);
+		}
+	}
+	Ok(())
+}
+
+fn read_content(game: Option<Game>, input: &Path, buf: &[u8]) -> eyre::Result<(Game, calmare::Content)> {
+	let name = input.file_name().and_then(|a| a.to_str()).unwrap_or_default();
+	if is_ani(name) {
+		let Some(game) = game else {
+			eyre::bail!("specify --game to decompile animation scripts")
+		};
+		eyre::ensure!(!game.is_ed7(), "animation scripts are only supported for the Sky games");
+		return Ok((game, calmare::Content::ED6Ani(themelios_ani::ed6::read_monster(game, buf)?)))
+	}
+	let Some(table) = Table::from_name(name) else {
+		return read_scena(game, input, buf)
+	};
+	let Some(game) = game else {
+		eyre::bail!("specify --game to decompile {table:?} tables")
+	};
+	Ok((game, read_table(game, table, buf)?))
+}
+
+/// Monster animation scripts are named like `as00010._dt`. The `asmag` and `asitem` files are
+/// named similarly, but are something else.
+fn is_ani(name: &str) -> bool {
+	let name = name.to_lowercase();
+	name.starts_with("as") && name.ends_with("._dt")
+		&& !name.starts_with("asmag") && !name.starts_with("asitem")
+}
+
+fn read_table(game: Game, table: Table, buf: &[u8]) -> eyre::Result<calmare::Content> {
+	use themelios::tables::*;
+	use calmare::Content as C;
+	Ok(match (table, game.is_ed7()) {
+		(Table::Name, false) => {
+			let (t1, t2) = name::ED6Name::read(game, buf)?;
+			C::ED6Name(t1, t2)
+		}
+		(Table::Name,  true)  => C::ED7Name(name::ED7Name::read(buf)?),
+		(Table::Quest, false) => C::ED6Quest(quest::ED6Quest::read(buf)?),
+		(Table::Quest, true)  => C::ED7Quest(quest::ED7Quest::read(buf)?),
+		(Table::Town,  _)     => C::Town(town::Town::read(game, buf)?),
+		(Table::World, false) => C::ED6World(world::ED6World::read(buf)?),
+		(Table::Bgm,   true)  => C::ED7Bgm(bgm::ED7Bgm::read(buf)?),
+		(Table::Sound, true)  => C::ED7Sound(se::ED7Sound::read(buf)?),
+		(Table::Ent,   false) => C::ED6Ent(ent::ED6Ent::read(buf)?),
+		_ => eyre::bail!("{table:?} tables do not exist in {game:?}"),
+	})
+}
+
+fn read_scena(game: Option<Game>, input: &Path, buf: &[u8]) -> eyre::Result<(Game, calmare::Content)> {
+	let game = match game {
+		Some(game) => game,
 		None => {
-			for game in [
-				Game::Fc, Game::Sc, Game::Tc, Game::ZeroKai, Game::AoKai, // Pc
-				Game::FcEvo, Game::ScEvo, Game::TcEvo, Game::ZeroEvo, Game::AoEvo, // Evo
-				Game::Zero, Game::Ao, // Geofront
-			] {
-				if game.is_ed7() {
-					if let Ok(scena) = ED7Scena::read(game, buf) {
-						return Ok(calmare::to_string(game, &calmare::Content::ED7Scena(scena), lookup))
-					}
-				} else {
-					if let Ok(scena) = ED6Scena::read(game, buf) {
-						return Ok(calmare::to_string(game, &calmare::Content::ED6Scena(scena), lookup))
-					}
-				}
+			let hints = detect::Hints {
+				filename: input.file_name().and_then(|a| a.to_str()).filter(|_| input.as_os_str() != "-"),
+				// Files extracted by factoria are placed in directories named after the archive
+				archive: input.parent()
+					.and_then(|a| a.file_name()?.to_str()?.strip_prefix("ED6_DT"))
+					.and_then(|a| u8::from_str_radix(a, 16).ok()),
+			};
+			let candidates = detect::detect(buf, hints);
+			let ties = detect::ties(&candidates);
+			let Some(best) = ties.first() else {
+				eyre::bail!("could not parse script; specify --game for more details")
+			};
+			if ties.len() > 1 {
+				let games = ties.iter().map(|a| format!("{:?}", a.game)).collect::<Vec<_>>().join(", ");
+				eprintln!("{}: ambiguous game ({games}), using {:?}; specify --game to override", input.display(), best.game);
 			}
-			eyre::bail!("could not parse script; specify --game for more details")
+			best.game
 		}
+	};
+	let c = if game.is_ed7() {
+		calmare::Content::ED7Scena(ED7Scena::read(game, buf)?)
+	} else {
+		calmare::Content::ED6Scena(ED6Scena::read(game, buf)?)
+	};
+	Ok((game, c))
+}
+
+fn write_content(game: Game, c: &calmare::Content) -> eyre::Result<Vec<u8>> {
+	use themelios::tables::*;
+	use calmare::Content as C;
+	Ok(match c {
+		C::ED6Scena(s) => ED6Scena::write(game, s)?,
+		C::ED7Scena(s) => ED7Scena::write(game, s)?,
+		C::ED6Name(t1, t2) => name::ED6Name::write(game, t1, t2)?,
+		C::ED7Name(t) => name::ED7Name::write(t)?,
+		C::ED6Quest(t) => quest::ED6Quest::write(t)?,
+		C::ED7Quest(t) => quest::ED7Quest::write(t)?,
+		C::Town(t) => town::Town::write(game, t)?,
+		C::ED6World(t) => world::ED6World::write(t)?,
+		C::ED7Bgm(t) => bgm::ED7Bgm::write(t)?,
+		C::ED7Sound(t) => se::ED7Sound::write(t)?,
+		C::ED6Ent(t) => ent::ED6Ent::write(t)?,
+		C::ED6Ani(ani) => themelios_ani::ed6::write_monster(game, ani)?,
+	})
+}
+
+/// Decompiles and recompiles a file, and checks that the result is identical to the original.
+fn check(cli: &Cli, input: &Path, lookup: Option<&dyn Lookup>) -> eyre::Result<usize> {
+	let mut buf = Vec::new();
+	get_input(input)?.read_to_end(&mut buf)?;
+
+	let (game, c) = read_content(cli.game, input, &buf)?;
+	let src = calmare::to_string_with_names(game, &c, lookup, &read_symbols(input)?);
+	let (val, diags) = calmare::parse(&src, lookup);
+	let Some((game, val)) = val else {
+		print_diags(&format!("{} (decompiled)", input.display()), &src, &diags);
+		eyre::bail!("decompiled script failed to parse with {} errors", diags.iter().filter(|a| a.is_fatal()).count())
+	};
+	let data = write_content(game, &val)?;
+
+	let Some(pos) = std::iter::zip(&buf, &data).position(|(a, b)| a != b)
+		.or_else(|| (buf.len() != data.len()).then_some(buf.len().min(data.len())))
+	else {
+		return Ok(diags.len())
+	};
+
+	fn dump(d: &[u8], pos: usize) -> gospel_dump::Dump<'_> {
+		let start = (pos & !15).saturating_sub(32).min(d.len());
+		gospel_dump::dump(&Reader::new(d)).start(start).end((start + 96).min(d.len()))
 	}
+	eyre::bail!(
+		"roundtrip differs at 0x{pos:X} (0x{:X} bytes originally, 0x{:X} after)\noriginal:\n{:.16X}rewritten:\n{:.16X}",
+		buf.len(), data.len(), dump(&buf, pos), dump(&data, pos),
+	)
 }
 
-fn cli_game(e: CliGame) -> Game {
-	match e {
-		CliGame::Fc      => Game::Fc,
-		CliGame::FcEvo   => Game::FcEvo,
-		CliGame::FcKai   => Game::FcKai,
-		CliGame::Sc      => Game::Sc,
-		CliGame::ScEvo   => Game::ScEvo,
-		CliGame::ScKai   => Game::ScKai,
-		CliGame::Tc      => Game::Tc,
-		CliGame::TcEvo   => Game::TcEvo,
-		CliGame::TcKai   => Game::TcKai,
-		CliGame::Zero    => Game::Zero,
-		CliGame::ZeroEvo => Game::ZeroEvo,
-		CliGame::ZeroKai => Game::ZeroKai,
-		CliGame::Ao      => Game::Ao,
-		CliGame::AoEvo   => Game::AoEvo,
-		CliGame::AoKai   => Game::AoKai,
+/// Reads the function names from the symbol file next to the input, `t0100.sym` for `t0100._sn`,
+/// if there is one.
+fn read_symbols(input: &Path) -> eyre::Result<calmare::FuncNames> {
+	let path = input.with_extension("sym");
+	if input.as_os_str() == "-" || !path.is_file() {
+		return Ok(Default::default())
 	}
+	let src = std::fs::read_to_string(&path)?;
+	calmare::parse_symbols(&src).map_err(|e| eyre::eyre!("{}: {e}", path.display()))
 }
 
 fn get_input(input: &Path) -> std::io::Result<Box<dyn Read>> {
@@ -228,6 +518,7 @@ fn get_output(output: Option<&Path>, input: &Path, suffix: &str) -> std::io::Res
 }
 
 pub fn print_diags(filename: &str, source: &str, diags: &[calmare::parse::Diag]) {
+	static LOCK: Mutex<()> = Mutex::new(());
 	use codespan_reporting::diagnostic::{Diagnostic, Label};
 	use codespan_reporting::files::SimpleFiles;
 	use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
@@ -237,6 +528,7 @@ pub fn print_diags(filename: &str, source: &str, diags: &[calmare::parse::Diag])
 	let mut files = SimpleFiles::new();
 	let file_id = files.add(filename, source);
 
+	let _guard = LOCK.lock().unwrap();
 	let mut diags = diags.to_owned();
 	diags.sort_by_key(|a| (a.text.0.start, a.text.0.end));
 
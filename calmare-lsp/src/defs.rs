@@ -0,0 +1,168 @@
+//! Finding definitions by looking at the tokens, since the parser does not keep track of where
+//! things are defined, and this should also work on files that do not parse.
+use calmare::parse::lex::{Line, Token};
+use calmare::span::{Span, Spanned as S};
+
+/// A term like `fn[3]` or `eff[1]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key<'a> {
+	pub term: &'a str,
+	pub index: u64,
+}
+
+/// Returns all definitions in the file.
+///
+/// Top-level items such as `fn[3]:`, `chip[0]`, or `npc char[2]:` define the term they start with,
+/// and `EffLoad eff[1] "..."` defines its effect.
+pub fn definitions<'a>(lines: &[Line<'a>]) -> Vec<(Key<'a>, Span)> {
+	let mut out = Vec::new();
+	for line in lines.iter().skip(1) {
+		let head = match line.head.as_slice() {
+			[S(_, Token::Ident("npc" | "monster")), rest@..] => rest,
+			head => head,
+		};
+		if let Some((key, s)) = term(head) {
+			out.push((key, s));
+		}
+	}
+	each_line(lines, &mut |line| {
+		if let [S(_, Token::Ident("EffLoad")), rest@..] = line.head.as_slice()
+			&& let Some((key, s)) = term(rest)
+			&& key.term == "eff"
+		{
+			out.push((key, s));
+		}
+	});
+	out
+}
+
+/// Returns the names defined in the file: constants like `const NAME = ...`, and named functions
+/// like `fn NAME:` or `fn[3] NAME:`.
+pub fn named_definitions<'a>(lines: &[Line<'a>]) -> Vec<(&'a str, Span)> {
+	let mut out = Vec::new();
+	for line in lines.iter().skip(1) {
+		match line.head.as_slice() {
+			[S(_, Token::Ident("const" | "alias")), S(s, Token::Ident(name)), ..]
+			| [S(_, Token::Ident("fn")), S(s, Token::Ident(name)), ..]
+			| [S(_, Token::Ident("fn")), S(_, Token::Bracket(_)), S(s, Token::Ident(name)), ..] => {
+				out.push((*name, *s));
+			}
+			_ => {}
+		}
+	}
+	out
+}
+
+/// Returns the term under the cursor, if any. Function references like `fn[0,3]` refer to the
+/// definition `fn[3]`.
+pub fn reference_at<'a>(lines: &[Line<'a>], offset: usize) -> Option<Key<'a>> {
+	let mut found = None;
+	each_line(lines, &mut |line| {
+		if found.is_none() {
+			found = find_in(&line.head, offset);
+		}
+	});
+	found
+}
+
+/// Returns the word under the cursor, if any.
+pub fn ident_at<'a>(lines: &[Line<'a>], offset: usize) -> Option<S<&'a str>> {
+	fn find<'a>(tokens: &[S<Token<'a>>], offset: usize) -> Option<S<&'a str>> {
+		let S(s, t) = tokens.iter().find(|S(s, _)| s.start <= offset && offset <= s.end)?;
+		match t {
+			Token::Ident(a) => Some(S(*s, *a)),
+			Token::Bracket(d) | Token::Paren(d) => find(&d.tokens, offset),
+			_ => None,
+		}
+	}
+	let mut found = None;
+	each_line(lines, &mut |line| {
+		if found.is_none() {
+			found = find(&line.head, offset);
+		}
+	});
+	found
+}
+
+fn find_in<'a>(tokens: &[S<Token<'a>>], offset: usize) -> Option<Key<'a>> {
+	for (i, S(s, t)) in tokens.iter().enumerate() {
+		if !(s.start <= offset && offset <= s.end) {
+			continue
+		}
+		match t {
+			Token::Ident(_) => {
+				if let Some((key, _)) = term(&tokens[i..]) {
+					return Some(key)
+				}
+			}
+			Token::Bracket(d) => {
+				if i > 0 && let Some((key, _)) = term(&tokens[i-1..]) {
+					return Some(key)
+				}
+				return find_in(&d.tokens, offset)
+			}
+			Token::Paren(d) => return find_in(&d.tokens, offset),
+			_ => {}
+		}
+	}
+	None
+}
+
+fn term<'a>(tokens: &[S<Token<'a>>]) -> Option<(Key<'a>, Span)> {
+	let [S(s1, Token::Ident(term)), S(s2, Token::Bracket(d)), ..] = tokens else {
+		return None
+	};
+	if !s1.connects(*s2) {
+		return None
+	}
+	let index = match d.tokens.as_slice() {
+		[S(_, Token::Int(n))] => *n,
+		[S(_, Token::Int(0)), S(_, Token::Comma), S(_, Token::Int(n))] if *term == "fn" => *n,
+		_ => return None,
+	};
+	Some((Key { term, index }, *s1 | *s2))
+}
+
+fn each_line<'a>(lines: &[Line<'a>], f: &mut impl FnMut(&Line<'a>)) {
+	for line in lines {
+		f(line);
+		if let Some(body) = &line.body {
+			each_line(body, f);
+		}
+	}
+}
+
+#[test]
+fn test() {
+	use calmare::parse::diag::diagnose;
+	let src = "\
+calmare fc scena
+const GREET = fn[0,3]
+fn[0]:
+	Call fn[0,3]
+	EffLoad eff[1] \"effect\"
+	Call fn[1,3]
+fn[3] greet:
+	EffPlay eff[1]
+";
+	let lines = diagnose(|| calmare::parse::lex::lex(src)).0;
+	let at = |s: &str| src.find(s).unwrap();
+	let defs = definitions(&lines);
+	let def = |term, index| defs.iter().find(|a| a.0 == Key { term, index }).map(|a| &src[a.1.as_range()]);
+	assert_eq!(def("fn", 0), Some("fn[0]"));
+	assert_eq!(def("fn", 3), Some("fn[3]"));
+	assert_eq!(def("eff", 1), Some("eff[1]"));
+
+	// fn[0,3] refers to fn[3], from anywhere in the term
+	let call = at("Call fn[0,3]") + 5;
+	for offset in [call, call + 2, call + 5] {
+		assert_eq!(reference_at(&lines, offset), Some(Key { term: "fn", index: 3 }));
+	}
+	assert_eq!(reference_at(&lines, at("eff[1]\n") + 1), Some(Key { term: "eff", index: 1 }));
+	// References to other scenas do not refer to this one's functions
+	assert_eq!(reference_at(&lines, at("fn[1,3]")), None);
+
+	let names = named_definitions(&lines);
+	assert_eq!(names.iter().map(|a| a.0).collect::<Vec<_>>(), ["GREET", "greet"]);
+	assert_eq!(ident_at(&lines, at("greet") + 2).map(|a| a.1), Some("greet"));
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use calmare::parse::lower::FileType;
+use themelios::types::Game;
+
+#[derive(Debug, Clone)]
+pub struct InsnInfo {
+	pub name: &'static str,
+	pub args: Vec<&'static str>,
+}
+
+impl InsnInfo {
+	pub fn signature(&self) -> String {
+		format!("{}({})", self.name, self.args.join(", "))
+	}
+}
+
+/// The games listed in the hover table. The Kai versions use the same instruction sets as the
+/// PC versions, so they are left out.
+const SCENA_GAMES: &[Game] = &[
+	Game::Fc, Game::FcEvo, Game::Sc, Game::ScEvo, Game::Tc, Game::TcEvo,
+	Game::Zero, Game::ZeroEvo, Game::Ao, Game::AoEvo,
+];
+const ANI_GAMES: &[Game] = &[Game::Fc, Game::Sc, Game::Tc];
+
+fn scena() -> Vec<InsnInfo> {
+	macro run([$(($ident:ident $(($_n:ident $ty:ty))*))*]) {
+		return vec![$(InsnInfo { name: stringify!($ident), args: vec![$(stringify!($ty)),*] }),*]
+	}
+	themelios::scena::code::introspect!(run);
+}
+
+fn ani() -> Vec<InsnInfo> {
+	macro run([$(($ident:ident $(($_n:ident $ty:ty))*))*]) {
+		return vec![$(InsnInfo { name: stringify!($ident), args: vec![$(stringify!($ty)),*] }),*]
+	}
+	themelios_ani::insn::introspect!(run);
+}
+
+pub struct Insns {
+	scena: Vec<InsnInfo>,
+	ani: Vec<InsnInfo>,
+}
+
+impl Insns {
+	pub fn new() -> Self {
+		Insns { scena: scena(), ani: ani() }
+	}
+
+	/// The instructions that can be used in a file of this type, or an empty list if the file type
+	/// has no code.
+	pub fn available(&self, game: Game, ty: FileType) -> impl Iterator<Item=&InsnInfo> {
+		let list: &[InsnInfo] = match ty {
+			FileType::Scena => &self.scena,
+			FileType::Ani => &self.ani,
+			_ => &[],
+		};
+		list.iter().filter(move |i| opcode(ty, game, i.name).is_some())
+	}
+
+	pub fn get(&self, ty: FileType, name: &str) -> Option<&InsnInfo> {
+		match ty {
+			FileType::Scena => self.scena.iter().find(|a| a.name == name),
+			FileType::Ani => self.ani.iter().find(|a| a.name == name),
+			_ => None,
+		}
+	}
+}
+
+pub fn opcode(ty: FileType, game: Game, name: &str) -> Option<u8> {
+	match ty {
+		FileType::Scena => themelios::scena::code::Insn::opcode(game, name),
+		FileType::Ani => themelios_ani::insn::Insn::opcode(game, name),
+		_ => None,
+	}
+}
+
+/// Markdown for hovering over an instruction: its signature, and a table of which byte it is
+/// encoded as in each game, like in the `Insn` documentation.
+pub fn hover(ty: FileType, game: Game, insn: &InsnInfo) -> String {
+	let games = if ty == FileType::Ani { ANI_GAMES } else { SCENA_GAMES };
+	let mut s = format!("```\n{}\n```\n\n", insn.signature());
+	if opcode(ty, game, insn.name).is_none() {
+		let _ = writeln!(s, "*Not available in `{}`.*\n", calmare::common::game(game));
+	}
+	let mut head = String::from("|");
+	let mut sep = String::from("|");
+	let mut row = String::from("|");
+	for &g in games {
+		let _ = write!(head, " {} |", calmare::common::game(g));
+		sep.push_str(" :-: |");
+		match opcode(ty, g, insn.name) {
+			Some(op) => { let _ = write!(row, " {op:02X} |"); }
+			None => row.push_str("  |"),
+		}
+	}
+	let _ = write!(s, "{head}\n{sep}\n{row}\n");
+	s
+}
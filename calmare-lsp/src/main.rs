@@ -0,0 +1,220 @@
+#![feature(let_chains)]
+#![feature(decl_macro)]
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+
+use calmare::parse::diag::{diagnose, Level};
+use calmare::parse::lex::{lex, Line};
+use calmare::parse::lower::{parse_type, FileType};
+use calmare::span::{Span, Spanned as S};
+use lsp_server::{Connection, Message, Notification, Request, Response, ErrorCode};
+use lsp_types::notification::{self as notif, Notification as _};
+use lsp_types::request::{self as req, Request as _};
+use lsp_types::*;
+use themelios::types::Game;
+
+mod pos;
+mod insn;
+mod defs;
+
+use pos::LineIndex;
+
+fn main() -> eyre::Result<()> {
+	let (conn, io) = Connection::stdio();
+	let caps = ServerCapabilities {
+		text_document_sync: Some(TextDocumentSyncKind::FULL.into()),
+		hover_provider: Some(true.into()),
+		definition_provider: Some(OneOf::Left(true)),
+		completion_provider: Some(CompletionOptions::default()),
+		..Default::default()
+	};
+	conn.initialize(serde_json::to_value(caps)?)?;
+	Server {
+		conn,
+		docs: HashMap::new(),
+		insns: insn::Insns::new(),
+	}.run()?;
+	io.join()?;
+	Ok(())
+}
+
+struct Server {
+	conn: Connection,
+	docs: HashMap<Url, String>,
+	insns: insn::Insns,
+}
+
+impl Server {
+	fn run(mut self) -> eyre::Result<()> {
+		while let Ok(msg) = self.conn.receiver.recv() {
+			match msg {
+				Message::Request(r) => {
+					if self.conn.handle_shutdown(&r)? {
+						break
+					}
+					let resp = self.request(r);
+					self.conn.sender.send(resp.into())?;
+				}
+				Message::Notification(n) => self.notification(n)?,
+				Message::Response(_) => {}
+			}
+		}
+		Ok(())
+	}
+
+	fn request(&self, r: Request) -> Response {
+		match r.method.as_str() {
+			req::HoverRequest::METHOD => handle::<req::HoverRequest>(r, |p| self.hover(p)),
+			req::GotoDefinition::METHOD => handle::<req::GotoDefinition>(r, |p| self.definition(p)),
+			req::Completion::METHOD => handle::<req::Completion>(r, |p| self.completion(p)),
+			_ => Response::new_err(r.id, ErrorCode::MethodNotFound as i32, format!("unknown request '{}'", r.method)),
+		}
+	}
+
+	fn notification(&mut self, n: Notification) -> eyre::Result<()> {
+		match n.method.as_str() {
+			notif::DidOpenTextDocument::METHOD => {
+				let p: DidOpenTextDocumentParams = serde_json::from_value(n.params)?;
+				self.docs.insert(p.text_document.uri.clone(), p.text_document.text);
+				self.publish_diagnostics(&p.text_document.uri)?;
+			}
+			notif::DidChangeTextDocument::METHOD => {
+				let p: DidChangeTextDocumentParams = serde_json::from_value(n.params)?;
+				// Using full sync, so the last change contains the whole text
+				if let Some(change) = p.content_changes.into_iter().last() {
+					self.docs.insert(p.text_document.uri.clone(), change.text);
+					self.publish_diagnostics(&p.text_document.uri)?;
+				}
+			}
+			notif::DidCloseTextDocument::METHOD => {
+				let p: DidCloseTextDocumentParams = serde_json::from_value(n.params)?;
+				self.docs.remove(&p.text_document.uri);
+				self.notify::<notif::PublishDiagnostics>(PublishDiagnosticsParams {
+					uri: p.text_document.uri,
+					diagnostics: Vec::new(),
+					version: None,
+				})?;
+			}
+			_ => {}
+		}
+		Ok(())
+	}
+
+	fn notify<N: notif::Notification>(&self, params: N::Params) -> eyre::Result<()> {
+		self.conn.sender.send(Notification::new(N::METHOD.to_owned(), params).into())?;
+		Ok(())
+	}
+
+	fn publish_diagnostics(&self, uri: &Url) -> eyre::Result<()> {
+		let src = &self.docs[uri];
+		let idx = LineIndex::new(src);
+		// Files in `use` are read from disk, relative to this file
+		let dir = uri.to_file_path().ok().and_then(|a| Some(a.parent()?.to_owned()));
+		let load = |path: &str| match &dir {
+			Some(dir) => std::fs::read_to_string(dir.join(path)),
+			None => Err(std::io::ErrorKind::Unsupported.into()),
+		};
+		let diags = match std::panic::catch_unwind(|| calmare::parse_with(src, None, &load).1) {
+			Ok(diags) => diags.iter().map(|d| Diagnostic {
+				range: idx.range(d.text.0),
+				severity: Some(match d.level {
+					Level::Error => DiagnosticSeverity::ERROR,
+					Level::Warning => DiagnosticSeverity::WARNING,
+					Level::Info => DiagnosticSeverity::INFORMATION,
+				}),
+				source: Some("calmare".to_owned()),
+				message: d.text.1.clone(),
+				related_information: (!d.notes.is_empty()).then(|| d.notes.iter().map(|n| {
+					DiagnosticRelatedInformation {
+						location: Location::new(uri.clone(), idx.range(n.0)),
+						message: n.1.clone(),
+					}
+				}).collect()),
+				..Default::default()
+			}).collect(),
+			Err(_) => vec![Diagnostic {
+				range: idx.range(Span::new_at(0)),
+				severity: Some(DiagnosticSeverity::ERROR),
+				source: Some("calmare".to_owned()),
+				message: "the compiler crashed on this file".to_owned(),
+				..Default::default()
+			}],
+		};
+		self.notify::<notif::PublishDiagnostics>(PublishDiagnosticsParams {
+			uri: uri.clone(),
+			diagnostics: diags,
+			version: None,
+		})
+	}
+
+	fn hover(&self, p: HoverParams) -> Option<Hover> {
+		let p = p.text_document_position_params;
+		let src = self.docs.get(&p.text_document.uri)?;
+		let idx = LineIndex::new(src);
+		let offset = idx.offset(p.position);
+		let lines = try_lex(src)?;
+		let (game, ty) = file_type(&lines)?;
+		let S(span, name) = defs::ident_at(&lines, offset)?;
+		let insn = self.insns.get(ty, name)?;
+		Some(Hover {
+			contents: HoverContents::Markup(MarkupContent {
+				kind: MarkupKind::Markdown,
+				value: insn::hover(ty, game, insn),
+			}),
+			range: Some(idx.range(span)),
+		})
+	}
+
+	fn definition(&self, p: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+		let p = p.text_document_position_params;
+		let src = self.docs.get(&p.text_document.uri)?;
+		let idx = LineIndex::new(src);
+		let offset = idx.offset(p.position);
+		let lines = try_lex(src)?;
+		let span = if let Some(key) = defs::reference_at(&lines, offset) {
+			defs::definitions(&lines).into_iter().find(|(k, _)| *k == key)?.1
+		} else {
+			let S(_, name) = defs::ident_at(&lines, offset)?;
+			defs::named_definitions(&lines).into_iter().find(|(k, _)| *k == name)?.1
+		};
+		Some(GotoDefinitionResponse::Scalar(Location::new(p.text_document.uri, idx.range(span))))
+	}
+
+	fn completion(&self, p: CompletionParams) -> Option<CompletionResponse> {
+		let p = p.text_document_position;
+		let src = self.docs.get(&p.text_document.uri)?;
+		let idx = LineIndex::new(src);
+		let offset = idx.offset(p.position);
+		// Only complete instruction names, which are the first word on indented lines
+		let before = idx.line_before(offset);
+		let word = before.trim_start();
+		if word.len() == before.len() || !word.chars().all(|c| c.is_alphanumeric() || c == '_') {
+			return None
+		}
+		let lines = try_lex(src)?;
+		let (game, ty) = file_type(&lines)?;
+		let items = self.insns.available(game, ty).map(|i| CompletionItem {
+			label: i.name.to_owned(),
+			kind: Some(CompletionItemKind::FUNCTION),
+			detail: Some(i.signature()),
+			..Default::default()
+		}).collect();
+		Some(CompletionResponse::Array(items))
+	}
+}
+
+fn handle<R: req::Request>(r: Request, f: impl FnOnce(R::Params) -> R::Result) -> Response {
+	match serde_json::from_value(r.params) {
+		Ok(p) => Response::new_ok(r.id, f(p)),
+		Err(e) => Response::new_err(r.id, ErrorCode::InvalidParams as i32, e.to_string()),
+	}
+}
+
+/// Lexes the file, ignoring any errors. Returns `None` if the lexer crashes.
+fn try_lex(src: &str) -> Option<Vec<Line<'_>>> {
+	std::panic::catch_unwind(AssertUnwindSafe(|| diagnose(|| lex(src)).0)).ok()
+}
+
+fn file_type(lines: &[Line]) -> Option<(Game, FileType)> {
+	diagnose(|| parse_type(lines.first()?).ok()).0
+}
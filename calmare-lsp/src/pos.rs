@@ -0,0 +1,72 @@
+use calmare::span::Span;
+use lsp_types::{Position, Range};
+
+/// Converts between byte offsets, which is what [`Span`] uses, and the line/UTF-16 column pairs
+/// used by the protocol.
+pub struct LineIndex<'a> {
+	src: &'a str,
+	lines: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+	pub fn new(src: &'a str) -> Self {
+		let mut lines = vec![0];
+		lines.extend(src.match_indices('\n').map(|(i, _)| i + 1));
+		LineIndex { src, lines }
+	}
+
+	pub fn position(&self, offset: usize) -> Position {
+		let offset = offset.min(self.src.len());
+		let line = self.lines.partition_point(|&a| a <= offset) - 1;
+		let col = self.src[self.lines[line]..offset].encode_utf16().count();
+		Position::new(line as u32, col as u32)
+	}
+
+	pub fn range(&self, span: Span) -> Range {
+		Range::new(self.position(span.start), self.position(span.end))
+	}
+
+	pub fn offset(&self, pos: Position) -> usize {
+		let Some(&start) = self.lines.get(pos.line as usize) else {
+			return self.src.len()
+		};
+		let line = self.src[start..].split('\n').next().unwrap_or_default();
+		let mut col = 0;
+		for (i, c) in line.char_indices() {
+			if col >= pos.character as usize {
+				return start + i
+			}
+			col += c.len_utf16();
+		}
+		start + line.len()
+	}
+
+	/// The text of the line containing `offset`, up to `offset`.
+	pub fn line_before(&self, offset: usize) -> &'a str {
+		let line = self.lines.partition_point(|&a| a <= offset) - 1;
+		&self.src[self.lines[line]..offset]
+	}
+}
+
+#[test]
+fn test() {
+	// 'é' is two bytes and one UTF-16 unit, '😀' is four bytes and two units
+	let src = "fn[0]:\n\tTextTalk \"é😀x\"\nend";
+	let index = LineIndex::new(src);
+	let x = src.rfind('x').unwrap();
+	assert_eq!(index.position(x), Position::new(1, 14));
+	assert_eq!(index.offset(Position::new(1, 14)), x);
+	assert_eq!(index.position(src.find('😀').unwrap()), Position::new(1, 12));
+	assert_eq!(index.offset(Position::new(1, 12)), src.find('😀').unwrap());
+	assert_eq!(index.line_before(x), "\tTextTalk \"é😀");
+
+	// Positions past the end of a line or the file are clamped
+	assert_eq!(index.offset(Position::new(0, 100)), src.find('\n').unwrap());
+	assert_eq!(index.offset(Position::new(2, 100)), src.len());
+	assert_eq!(index.offset(Position::new(10, 0)), src.len());
+	assert_eq!(index.position(src.len() + 10), Position::new(2, 3));
+
+	for offset in (0..=src.len()).filter(|a| src.is_char_boundary(*a)) {
+		assert_eq!(index.offset(index.position(offset)), offset);
+	}
+}
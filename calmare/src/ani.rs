@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use themelios::types::*;
+use themelios_ani::Addr;
+use themelios_ani::ed6::Ani;
+use themelios_ani::insn::{Insn, CharId, XId};
+use crate::writer::Context;
+use crate::common::{self, ContextExt, Val};
+
+pub fn write(f: &mut Context, ani: &Ani) {
+	let g = common::game(f.game);
+	f.kw("calmare").kw(g).kw("ani").line();
+	f.line();
+
+	for (i, (ch, cp)) in ani.chips.iter().enumerate() {
+		f.val(&ChipId(i as u16)).val(ch).val(cp).line();
+	}
+	for model in &ani.models {
+		f.kw("model").val(model).line();
+	}
+	if let Some((x, bones)) = &ani.bones {
+		f.kw("bones").val(x).val(bones).line();
+	}
+	f.kw("sprite_offsets");
+	for (a, b) in &ani.sprite_offsets {
+		f.pre("(").val(a).suf(",").val(b).suf(")");
+	}
+	f.line();
+	f.line();
+
+	// Addresses are replaced with label numbers, in order of appearance. Labels can only be placed
+	// between instructions, so addresses inside an instruction are written as an offset from the
+	// start of it.
+	let starts = ani.insns.iter().map(|a| a.0).collect::<BTreeSet<_>>();
+	let start = |a: &Addr| *starts.range(..=*a).next_back().expect("address is outside the code");
+	let mut labels = BTreeMap::new();
+	let mut targets = ani.funcs.iter().map(start).collect::<Vec<_>>();
+	for (_, insn) in &ani.insns {
+		insn.clone().visit_addrs(|a| targets.push(start(a)));
+	}
+	targets.sort();
+	for a in targets {
+		let n = labels.len();
+		labels.entry(a).or_insert(n);
+	}
+	let label = |a: &Addr| {
+		let s = start(a);
+		label_addr(labels[&s], a.0 - s.0)
+	};
+
+	for (i, func) in ani.funcs.iter().enumerate() {
+		write!(f, "fn[{i}]");
+		f.space().val(&label(func)).line();
+	}
+	f.line();
+
+	f.kw("code").suf(":").line().indent(|f| {
+		for (addr, insn) in &ani.insns {
+			if let Some(l) = labels.get(addr) {
+				f.pre("@").val(&label_addr(*l, 0)).line();
+			}
+			let mut insn = insn.clone();
+			insn.visit_addrs(|a| *a = label(a));
+			write_insn(f, &insn);
+		}
+	});
+}
+
+fn write_insn(f: &mut Context, i: &Insn) {
+	macro run([$(($ident:ident $(($_n:ident $ty:ty))*))*]) {
+		match i {
+			$(Insn::$ident($($_n),*) => {
+				f.kw(stringify!($ident));
+				$(f.val($_n);)*
+			})*
+		}
+	}
+	themelios_ani::insn::introspect!(run);
+	f.line();
+}
+
+// In .clm files, an address is a label number and an offset into the instruction following the
+// label, packed into a single `Addr` so that instructions can be written and parsed as they are.
+// File addresses are 16 bits, so that is all the offset needs.
+pub(crate) const OFFSET_BITS: u32 = 16;
+
+pub(crate) fn label_addr(label: usize, offset: usize) -> Addr {
+	Addr(label << OFFSET_BITS | offset)
+}
+
+pub(crate) fn split_addr(a: Addr) -> (usize, usize) {
+	(a.0 >> OFFSET_BITS, a.0 & ((1 << OFFSET_BITS) - 1))
+}
+
+impl Val for Addr {
+	fn write(&self, f: &mut Context) {
+		match split_addr(*self) {
+			(n, 0) => write!(f, "L{n}"),
+			(n, o) => write!(f, "L{n}+{o}"),
+		}
+	}
+}
+
+impl Val for CharId {
+	fn write(&self, f: &mut Context) {
+		match self.0 {
+			0xFF => write!(f, "self"),
+			0xFE => write!(f, "target"),
+			n => write!(f, "char[{n}]"),
+		}
+	}
+}
+
+impl Val for XId {
+	fn write(&self, f: &mut Context) {
+		write!(f, "x[{}]", self.0)
+	}
+}
+
+#[test]
+fn test_inside() -> Result<(), Box<dyn std::error::Error>> {
+	use themelios_ani::ed6::{read_monster, write_monster};
+	use crate::Content;
+
+	let ani = Ani {
+		chips: Vec::new(),
+		models: vec!["model".to_owned()],
+		bones: None,
+		sprite_offsets: [(0, 0); 8],
+		funcs: vec![Addr(0)],
+		insns: vec![
+			(Addr(0), Insn::Goto(Addr(11))),
+			(Addr(10), Insn::Goto(Addr(0))),
+			(Addr(20), Insn::End()),
+		],
+	};
+	let data = write_monster(Game::Fc, &ani)?;
+	let src = crate::to_string(Game::Fc, &Content::ED6Ani(read_monster(Game::Fc, &data)?), None);
+	assert!(src.contains("Goto L1+1"), "{src}");
+	let (v, diags) = crate::parse(&src, None);
+	assert!(diags.is_empty(), "{src}\n{diags:?}");
+	let Some((_, Content::ED6Ani(ani))) = v else { panic!("{src}") };
+	assert!(write_monster(Game::Fc, &ani)? == data, "{src}");
+	Ok(())
+}
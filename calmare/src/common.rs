@@ -252,6 +252,7 @@ prim_arg!(u32, "{}");
 prim_arg!(i8, "{}");
 prim_arg!(i16, "{}");
 prim_arg!(i32, "{}");
+prim_arg!(bool, "{}");
 
 impl Val for f32 {
 	fn write(&self, f: &mut Context) {
@@ -398,6 +399,12 @@ impl Val for glam::Vec3 {
 	}
 }
 
+impl Val for glam::IVec2 {
+	fn write(&self, f: &mut Context) {
+		f.pre("(").val(&self.x).suf(",").val(&self.y).suf(")");
+	}
+}
+
 impl Val for Text {
 	fn write(&self, f: &mut Context) {
 		text(f, self)
@@ -406,7 +413,11 @@ impl Val for Text {
 
 impl Val for FuncId {
 	fn write(&self, f: &mut Context) {
-		write!(f, "fn[{},{}]", self.0, self.1)
+		if self.0 == 0 && let Some(name) = f.func_name(self.1) {
+			write!(f, "{name}")
+		} else {
+			write!(f, "fn[{},{}]", self.0, self.1)
+		}
 	}
 }
 
@@ -575,7 +586,7 @@ fn text(f: &mut Context, v: &Text) {
 	}
 }
 
-pub(crate) fn game(game: Game) -> &'static str {
+pub fn game(game: Game) -> &'static str {
 	use Game::*;
 	match game {
 		Fc      => "fc",
@@ -128,6 +128,9 @@ pub fn write(f: &mut Context, scena: &Scena) {
 			f.line();
 		}
 		write!(f, "fn[{i}]");
+		if let Some(name) = f.func_name(i as u16) {
+			f.space().kw(name);
+		}
 		common::func(f, func);
 	}
 }
@@ -6,10 +6,15 @@
 
 pub mod ed6;
 pub mod ed7;
+pub mod tables;
+pub mod ani;
 mod writer;
 pub mod common;
 
+use std::collections::BTreeMap;
+
 use themelios::{types::Game, lookup::Lookup};
+use themelios::tables::{name::*, quest::*, town::*, world::*, bgm::*, se::*, ent::*};
 pub use writer::Context;
 
 pub mod span;
@@ -19,21 +24,83 @@ pub mod parse;
 pub enum Content {
 	ED6Scena(themelios::scena::ed6::Scena),
 	ED7Scena(themelios::scena::ed7::Scena),
+	/// The two halves of the table; the second one is always empty in FC.
+	ED6Name(Vec<ED6Name>, Vec<ED6Name>),
+	ED7Name(Vec<ED7Name>),
+	ED6Quest(Vec<ED6Quest>),
+	ED7Quest(Vec<ED7Quest>),
+	Town(Vec<Town>),
+	ED6World(Vec<ED6World>),
+	ED7Bgm(Vec<ED7Bgm>),
+	ED7Sound(Vec<ED7Sound>),
+	ED6Ent(Vec<ED6Ent>),
+	ED6Ani(themelios_ani::ed6::Ani),
 }
 
+/// Names for a scena's own functions, so that they are written as `fn[3] talk_to_mayor:` and
+/// `talk_to_mayor` rather than `fn[3]:` and `fn[0,3]`.
+pub type FuncNames = BTreeMap<u16, String>;
+
 pub fn to_string(game: Game, c: &Content, lookup: Option<&dyn Lookup>) -> String {
-	let mut ctx = Context::new(game, lookup);
+	to_string_with_names(game, c, lookup, &FuncNames::new())
+}
+
+/// Like [`to_string`], but with names for the scena's functions.
+pub fn to_string_with_names(game: Game, c: &Content, lookup: Option<&dyn Lookup>, names: &FuncNames) -> String {
+	let mut ctx = Context::new(game, lookup).func_names(names);
 	match c {
 		Content::ED6Scena(scena) => ed6::write(&mut ctx, scena),
 		Content::ED7Scena(scena) => ed7::write(&mut ctx, scena),
+		Content::ED6Name(t1, t2) => tables::write_ed6_name(&mut ctx, t1, t2),
+		Content::ED7Name(t) => tables::write_ed7_name(&mut ctx, t),
+		Content::ED6Quest(t) => tables::write_ed6_quest(&mut ctx, t),
+		Content::ED7Quest(t) => tables::write_ed7_quest(&mut ctx, t),
+		Content::Town(t) => tables::write_town(&mut ctx, t),
+		Content::ED6World(t) => tables::write_ed6_world(&mut ctx, t),
+		Content::ED7Bgm(t) => tables::write_ed7_bgm(&mut ctx, t),
+		Content::ED7Sound(t) => tables::write_ed7_sound(&mut ctx, t),
+		Content::ED6Ent(t) => tables::write_ed6_ent(&mut ctx, t),
+		Content::ED6Ani(ani) => ani::write(&mut ctx, ani),
 	}
 	ctx.finish()
 }
 
+/// Reads a symbol file, which names functions with lines like `fn[3] talk_to_mayor`.
+pub fn parse_symbols(src: &str) -> Result<FuncNames, String> {
+	use unicode_xid::UnicodeXID;
+	let mut names = FuncNames::new();
+	for (i, line) in src.lines().enumerate() {
+		let line = line.split("//").next().unwrap().trim();
+		if line.is_empty() {
+			continue
+		}
+		let err = || format!("line {}: expected 'fn[n] name'", i + 1);
+		let (n, name) = line.strip_prefix("fn[").and_then(|a| a.split_once(']')).ok_or_else(err)?;
+		let n: u16 = n.trim().parse().map_err(|_| err())?;
+		let name = name.trim();
+		let mut chars = name.chars();
+		if !chars.next().is_some_and(|c| c.is_xid_start() || c == '_') || !chars.all(|c| c.is_xid_continue()) {
+			return Err(err())
+		}
+		if names.values().any(|a| a == name) {
+			return Err(format!("line {}: '{name}' is used twice", i + 1))
+		}
+		if names.insert(n, name.to_owned()).is_some() {
+			return Err(format!("line {}: fn[{n}] is named twice", i + 1))
+		}
+	}
+	Ok(names)
+}
+
 pub fn parse(src: &str, lookup: Option<&dyn Lookup>) -> (Option<(Game, crate::Content)>, Vec<parse::Diag>) {
+	parse_with(src, lookup, &|_| Err(std::io::ErrorKind::Unsupported.into()))
+}
+
+/// Like [`parse`], but with a function to read the files mentioned in `use`.
+pub fn parse_with(src: &str, lookup: Option<&dyn Lookup>, load: parse::lower::consts::Loader) -> (Option<(Game, crate::Content)>, Vec<parse::Diag>) {
 	let (v, diag) = parse::diag::diagnose(|| {
 		let tok = parse::lex::lex(src);
-		parse::lower::parse(&tok, lookup)
+		parse::lower::parse(&tok, lookup, load)
 	});
 	if diag.iter().any(|a| a.is_fatal()) {
 		(None, diag)
@@ -0,0 +1,347 @@
+//! Formatting of Calmare source.
+//!
+//! This works on the token tree from the lexer rather than on the parsed file, so comments and
+//! the overall layout are kept. What it does normalize is indentation, spacing between tokens, and
+//! the syntax of brace directives in text.
+use super::diag::{diagnose, Diag};
+use super::lex::{lex, Line, Token, TextToken, Delimited};
+use crate::span::{Span, Spanned as S};
+
+/// Formats the file, returning `None` if it could not be lexed.
+pub fn format(src: &str) -> (Option<String>, Vec<Diag>) {
+	let (lines, mut diags) = diagnose(|| lex(src));
+	if diags.iter().any(|a| a.is_fatal()) {
+		return (None, diags)
+	}
+
+	let mut f = Fmt::new(src);
+	f.lines(&lines, 0, false);
+	f.between(src.len(), 0);
+	if f.open {
+		f.out.push('\n');
+	}
+
+	let (_, check) = diagnose(|| lex(&f.out));
+	if check.iter().any(|a| a.is_fatal()) {
+		diags.push(Diag::error(Span::new_at(0), "formatting produced invalid output; this is a bug"));
+		return (None, diags)
+	}
+	(Some(f.out), diags)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delim {
+	None,
+	Paren,
+	Bracket,
+}
+
+enum Space<'a> {
+	Newline,
+	Comment(usize, &'a str),
+}
+
+struct Fmt<'a> {
+	src: &'a str,
+	out: String,
+	/// How far into the source has been written.
+	pos: usize,
+	/// Whether the current output line has any content.
+	open: bool,
+	/// Indentation of the current output line.
+	depth: usize,
+	/// Indentation of the last line that was written, so that comments at the end of a block can
+	/// stay in that block.
+	last_depth: usize,
+}
+
+impl<'a> Fmt<'a> {
+	fn new(src: &'a str) -> Self {
+		Fmt { src, out: String::new(), pos: 0, open: false, depth: 0, last_depth: 0 }
+	}
+
+	fn begin_line(&mut self, depth: usize) {
+		if self.open {
+			self.out.push('\n');
+		}
+		for _ in 0..depth {
+			self.out.push('\t');
+		}
+		self.open = true;
+		self.depth = depth;
+	}
+
+	fn blank_line(&mut self) {
+		if self.out.is_empty() {
+			return
+		}
+		if self.open {
+			self.out.push('\n');
+			self.open = false;
+		}
+		if !self.out.ends_with("\n\n") {
+			self.out.push('\n');
+		}
+	}
+
+	fn column(&self, pos: usize) -> usize {
+		pos - self.src[..pos].rfind('\n').map_or(0, |a| a + 1)
+	}
+
+	/// Consumes the source up to `end`, which should only be whitespace and comments.
+	fn space(&mut self, end: usize) -> Vec<Space<'a>> {
+		let mut out = Vec::new();
+		let text = &self.src[self.pos..end];
+		let mut i = 0;
+		while i < text.len() {
+			let rest = &text[i..];
+			if rest.starts_with("//") {
+				let len = rest.find('\n').unwrap_or(rest.len());
+				out.push(Space::Comment(self.pos + i, rest[..len].trim_end()));
+				i += len;
+			} else {
+				if rest.starts_with('\n') {
+					out.push(Space::Newline);
+				}
+				i += rest.chars().next().map_or(1, char::len_utf8);
+			}
+		}
+		self.pos = end;
+		out
+	}
+
+	/// Writes the comments and blank lines before a line starting at `end`.
+	fn between(&mut self, end: usize, depth: usize) {
+		let col = self.column(end);
+		let mut newlines = 0;
+		for s in self.space(end) {
+			match s {
+				Space::Newline => newlines += 1,
+				Space::Comment(at, c) => {
+					if newlines == 0 && self.open {
+						self.out.push(' ');
+					} else {
+						if newlines >= 2 {
+							self.blank_line();
+						}
+						// Comments indented further than the following line belong to the previous block
+						let depth = if self.column(at) > col { self.last_depth.max(depth) } else { depth };
+						self.begin_line(depth);
+					}
+					self.out.push_str(c);
+					newlines = 0;
+				}
+			}
+		}
+		if newlines >= 2 {
+			self.blank_line();
+		}
+	}
+
+	/// Writes the comments between two tokens on the same logical line, returning whether the
+	/// line was broken.
+	fn within(&mut self, space: Vec<Space>, depth: usize) -> bool {
+		let mut broke = false;
+		for s in space {
+			match s {
+				Space::Newline => broke = true,
+				Space::Comment(_, c) => {
+					if broke {
+						self.begin_line(depth);
+					} else {
+						self.out.push(' ');
+					}
+					self.out.push_str(c);
+					broke = true;
+				}
+			}
+		}
+		if broke {
+			self.begin_line(depth);
+		}
+		broke
+	}
+
+	fn lines(&mut self, lines: &[Line], depth: usize, first_inline: bool) {
+		for (i, line) in lines.iter().enumerate() {
+			let start = line_start(line);
+			let chained = if i == 0 {
+				first_inline
+			} else {
+				let prev = &lines[i-1];
+				prev.body.is_none()
+					&& &self.src[prev.eol.as_range()] == ";"
+					&& !self.src[prev.eol.end..start].contains('\n')
+			};
+			if chained {
+				self.space(start);
+				self.out.push_str(if i == 0 { " " } else { "; " });
+			} else {
+				self.between(start, depth);
+				self.begin_line(depth);
+			}
+			self.line(line, depth);
+		}
+	}
+
+	fn line(&mut self, line: &Line, depth: usize) {
+		self.tokens(&line.head, depth, Delim::None);
+		self.last_depth = depth;
+		let space = self.space(line.eol.start);
+		self.within(space, depth + 1);
+		self.pos = line.eol.end;
+		if let Some(body) = &line.body {
+			self.out.push(':');
+			let inline = body.first().is_some_and(|a| !self.src[line.eol.end..line_start(a)].contains('\n'));
+			self.lines(body, depth + 1, inline);
+		}
+	}
+
+	fn tokens(&mut self, tokens: &[S<Token>], depth: usize, delim: Delim) {
+		for (i, S(s, t)) in tokens.iter().enumerate() {
+			let space = self.space(s.start);
+			let prev = i.checked_sub(1).map(|i| &tokens[i]);
+			// Text pages are always written as `} {`
+			if let Some(S(_, Token::Brace(_))) = prev
+				&& let Token::Brace(_) = t
+				&& space.iter().all(|a| matches!(a, Space::Newline))
+			{
+				self.out.push(' ');
+			} else if !self.within(space, depth + 1) && let Some(S(p, pt)) = prev {
+				let space = match (pt, t) {
+					(_, Token::Comma) => false,
+					(Token::Comma, _) => delim != Delim::Bracket,
+					_ => !p.connects(*s),
+				};
+				if space {
+					self.out.push(' ');
+				}
+			}
+			self.token(*s, t, depth);
+		}
+	}
+
+	fn token(&mut self, s: Span, t: &Token, depth: usize) {
+		match t {
+			Token::Paren(d) => self.delimited(d, ('(', ')'), depth, Delim::Paren),
+			Token::Bracket(d) => self.delimited(d, ('[', ']'), depth, Delim::Bracket),
+			Token::Brace(d) => self.text(d),
+			_ => self.out.push_str(&self.src[s.as_range()]),
+		}
+		self.pos = s.end;
+	}
+
+	fn delimited(&mut self, d: &Delimited<Token>, (open, close): (char, char), depth: usize, delim: Delim) {
+		self.out.push(open);
+		self.pos = d.open.end;
+		self.tokens(&d.tokens, depth, delim);
+		let space = self.space(d.close.start);
+		self.within(space, depth);
+		self.out.push(close);
+	}
+
+	/// Text is copied mostly verbatim, since whitespace is significant in it. Only the indentation
+	/// and the brace directives are changed.
+	fn text(&mut self, d: &Delimited<TextToken>) {
+		let src = self.src;
+		let mut text = String::new();
+		let mut pos = d.open.start;
+		for S(_, t) in &d.tokens {
+			if let TextToken::Brace(d) = t && let Some(v) = self.directive(d) {
+				text.push_str(&src[pos..d.open.start]);
+				text.push_str(&v);
+				pos = d.close.end;
+			}
+		}
+		text.push_str(&src[pos..d.close.end]);
+
+		if !text.contains('\n') {
+			self.out.push_str(&text);
+			return
+		}
+
+		let depth = self.depth;
+		let mut lines = text.split('\n').peekable();
+		let first = lines.next().unwrap();
+		self.out.push_str(if first.trim_end() == "{" { "{" } else { first });
+		while let Some(line) = lines.next() {
+			let line = line.trim_start_matches([' ', '\t']);
+			self.out.push('\n');
+			if line.is_empty() {
+				continue
+			}
+			let is_last = lines.peek().is_none();
+			let depth = if is_last && line == "}" { depth } else { depth + 1 };
+			for _ in 0..depth {
+				self.out.push('\t');
+			}
+			self.out.push_str(line);
+		}
+	}
+
+	/// Returns the normalized form of a brace directive, such as `{item[3]}` for `{item item[3]}`.
+	fn directive(&self, d: &Delimited<Token>) -> Option<String> {
+		if self.src[d.open.start..d.close.end].contains('\n') {
+			return None
+		}
+		let tokens = match d.tokens.as_slice() {
+			[S(_, Token::Int(n))] if *n <= 0xFF => return Some(format!("{{0x{n:02X}}}")),
+			[S(_, Token::Ident("item")), rest@..]
+				if matches!(rest, [S(_, Token::Ident("item")), S(_, Token::Bracket(_))]) => rest,
+			tokens => tokens,
+		};
+		let mut f = Fmt::new(self.src);
+		f.pos = tokens.first().map_or(d.open.end, |a| a.0.start);
+		f.tokens(tokens, 0, Delim::None);
+		Some(format!("{{{}}}", f.out))
+	}
+}
+
+fn line_start(line: &Line) -> usize {
+	line.head.first().map_or(line.eol.start, |a| a.0.start)
+}
+
+#[test]
+fn test() {
+	let src = "\
+// header comment
+calmare  fc   scena
+
+fn[0]: // the first function
+	Foo  char[0] ( 1,2 , 3 ) fn[0, 1]
+	    Bar -3 100ms // trailing
+
+
+	// about this one
+	TextTalk char[0] {
+	 Hello{item item[3]}{ wait }
+	    world{13}
+	  } {
+	  Page two
+	}
+  // end of fn[0]
+fn[1]: Return; Return
+";
+	let expected = "\
+// header comment
+calmare fc scena
+
+fn[0]: // the first function
+	Foo char[0] (1, 2, 3) fn[0,1]
+		Bar -3 100ms // trailing
+
+	// about this one
+	TextTalk char[0] {
+		Hello{item[3]}{wait}
+		world{0x0D}
+	} {
+		Page two
+	}
+	// end of fn[0]
+fn[1]: Return; Return
+";
+	let (out, diags) = format(src);
+	assert!(diags.is_empty(), "{diags:?}");
+	assert_eq!(out.as_deref(), Some(expected));
+	assert_eq!(format(expected).0.as_deref(), Some(expected));
+}
@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use glam::{Vec3, Mat4};
+use glam::{Vec3, IVec2, Mat4};
 use themelios::text::{Text, TextSegment};
 use themelios::types::*;
 use themelios::lookup::Lookup;
@@ -11,10 +11,23 @@ use super::lex::{Line, Token, TextToken};
 use crate::span::{Spanned as S, Span};
 
 pub mod scena;
+pub mod tables;
+pub mod ani;
+pub mod consts;
+pub mod include;
+pub mod names;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
 	Scena,
+	Name,
+	Quest,
+	Town,
+	World,
+	Bgm,
+	Sound,
+	Ent,
+	Ani,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -33,6 +46,7 @@ pub struct Context<'a> {
 	pub game: Game,
 	pub ty: FileType,
 	pub lookup: &'a dyn Lookup,
+	pub consts: &'a consts::Consts<'a>,
 }
 
 impl<'a> std::fmt::Debug for Context<'a> {
@@ -175,7 +189,9 @@ impl<'a> Parse<'a> {
 	}
 
 	fn term<T: ValComma>(&mut self, name: &str) -> Result<Option<T>> {
-		if self.word(name) {
+		if let Some(v) = self.expand(|p| p.term(name))? {
+			Ok(Some(v))
+		} else if self.word(name) {
 			let space = self.space();
 			if let Some(d) = test!(self, Token::Bracket(d) => d) {
 				if let Some(s) = space {
@@ -215,7 +231,7 @@ impl<T: TryVal> Val for T {
 		if let Some(v) = Self::try_parse(p)? {
 			Ok(v)
 		} else {
-			Diag::error(p.next_span(), format_args!("expected {}", Self::desc())).emit();
+			p.note_const(Diag::error(p.next_span(), format_args!("expected {}", Self::desc()))).emit();
 			Err(Error)
 		}
 	}
@@ -330,6 +346,10 @@ fn parse_unit(p: &mut Parse) -> Result<S<Unit>> {
 }
 
 fn parse_int(p: &mut Parse) -> Result<Option<(S<i64>, S<Unit>)>> {
+	if let Some((S(_, v), S(_, u))) = p.expand(parse_int)? {
+		let s = p.prev_span();
+		return Ok(Some((S(s, v), S(s, u))))
+	}
 	match p.remaining() {
 		[S(s, Token::Int(n)), ..] => {
 			p.pos += 1;
@@ -346,6 +366,10 @@ fn parse_int(p: &mut Parse) -> Result<Option<(S<i64>, S<Unit>)>> {
 }
 
 fn parse_float(p: &mut Parse) -> Result<Option<(S<f64>, S<Unit>)>> {
+	if let Some((S(_, v), S(_, u))) = p.expand(parse_float)? {
+		let s = p.prev_span();
+		return Ok(Some((S(s, v), S(s, u))))
+	}
 	match p.remaining() {
 		[S(s, Token::Float(n)), ..] => {
 			p.pos += 1;
@@ -415,6 +439,20 @@ newtype_val!(TcMembers);
 
 newtype_val!(QuestTask);
 
+impl TryVal for bool {
+	fn desc() -> String { "'true', 'false'".to_owned() }
+
+	fn try_parse(p: &mut Parse) -> Result<Option<Self>> {
+		if p.word("true") {
+			Ok(Some(true))
+		} else if p.word("false") {
+			Ok(Some(false))
+		} else {
+			Ok(None)
+		}
+	}
+}
+
 impl TryVal for String {
 	fn desc() -> String { "string".to_owned() }
 
@@ -597,6 +635,18 @@ impl TryVal for Pos2 {
 	}
 }
 
+impl TryVal for IVec2 {
+	fn desc() -> String { "pos2".to_owned() }
+
+	fn try_parse(p: &mut Parse) -> Result<Option<Self>> {
+		if let Some((x, y)) = p.tuple()? {
+			Ok(Some(IVec2 { x, y }))
+		} else {
+			Ok(None)
+		}
+	}
+}
+
 impl TryVal for Vec3 {
 	fn desc() -> String { "pos3".to_owned() }
 
@@ -810,11 +860,12 @@ macro parse_data($d:ident => { $($k:ident $(=> $e:expr)?),* $(,)? }) {
 	});)*
 }
 
-fn parse_type(line: &Line) -> Result<(Game, FileType)> {
+pub fn parse_type(line: &Line) -> Result<(Game, FileType)> {
 	let dummy_ctx = &Context {
 		game: Game::Fc,
 		ty: FileType::Scena,
 		lookup: &themelios::lookup::NullLookup,
+		consts: &consts::Consts::new(),
 	};
 	Parse::new(line, dummy_ctx).parse_with(|p| {
 		if !p.word("calmare") {
@@ -842,11 +893,30 @@ fn parse_type(line: &Line) -> Result<(Game, FileType)> {
 		};
 		let ty = match *b {
 			"scena" => FileType::Scena,
+			"name" => FileType::Name,
+			"quest" => FileType::Quest,
+			"town" => FileType::Town,
+			"world" => FileType::World,
+			"bgm" => FileType::Bgm,
+			"sound" => FileType::Sound,
+			"ent" => FileType::Ent,
+			"ani" => FileType::Ani,
 			_ => {
 				Diag::error(p.prev_span(), "unknown file type").emit();
 				return Err(Error);
 			}
 		};
+		match ty {
+			FileType::World | FileType::Ent | FileType::Ani if game.is_ed7() => {
+				Diag::error(p.prev_span(), "this file type only exists in the Sky games").emit();
+				return Err(Error);
+			}
+			FileType::Bgm | FileType::Sound if !game.is_ed7() => {
+				Diag::error(p.prev_span(), "this file type only exists in the Crossbell games").emit();
+				return Err(Error);
+			}
+			_ => {}
+		}
 		Ok((game, ty))
 	})
 }
@@ -933,27 +1003,36 @@ impl<K: Ord, V> Many<K, V> {
 	}
 }
 
-pub fn parse(lines: &[Line], lookup: Option<&dyn Lookup>) -> Result<(Game, crate::Content)> {
+pub fn parse(lines: &[Line], lookup: Option<&dyn Lookup>, load: consts::Loader) -> Result<(Game, crate::Content)> {
 	if lines.is_empty() {
 		Diag::error(Span::new_at(0), "no type declaration").emit();
 		return Err(Error);
 	}
 
 	let (game, ty) = parse_type(&lines[0])?;
+	let (decls, mut lines, files) = consts::declarations(&lines[1..], load);
+	let funcs = if ty == FileType::Scena { names::assign(&mut lines) } else { Vec::new() };
+	let mut used = consts::lex_used(&files);
+	let mut used_funcs = include::names(ty, &files, &mut used);
+	include::renumber(&lines, &files, &mut used, &mut used_funcs);
+	let included = include::functions(ty, &files, &used);
 	let ctx = &Context {
 		game,
 		ty,
 		lookup: lookup.unwrap_or_else(|| themelios::lookup::default_for(game)),
+		consts: &consts::collect(&decls, &funcs, &files, &used, &used_funcs),
 	};
 
 	match ty {
 		FileType::Scena => {
 			if game.is_ed7() {
-				Ok((game, crate::Content::ED7Scena(scena::ed7::parse(&lines[1..], ctx)?)))
+				Ok((game, crate::Content::ED7Scena(scena::ed7::parse(&lines, &included, ctx)?)))
 			} else {
-				Ok((game, crate::Content::ED6Scena(scena::ed6::parse(&lines[1..], ctx)?)))
+				Ok((game, crate::Content::ED6Scena(scena::ed6::parse(&lines, &included, ctx)?)))
 			}
 		}
+		FileType::Ani => Ok((game, crate::Content::ED6Ani(ani::parse(&lines, ctx)?))),
+		_ => Ok((game, tables::parse(&lines, ctx)?)),
 	}
 }
 
@@ -963,7 +1042,7 @@ fn main() {
 	let src = include_str!("/tmp/kiseki/ao_gf_en/c1200");
 	let (v, diag) = super::diag::diagnose(|| {
 		let tok = crate::parse::lex::lex(src);
-		parse(&tok, None)
+		parse(&tok, None, &|_| Err(std::io::ErrorKind::Unsupported.into()))
 	});
 	println!("{:#?}", v);
 	super::diag::print_diags("<input>", src, &diag);
@@ -0,0 +1,232 @@
+use themelios_ani::Addr;
+use themelios_ani::ed6::Ani;
+use themelios_ani::insn::{Insn, CharId, XId};
+
+use super::*;
+use super::scena::FuncDefId;
+use crate::ani::{OFFSET_BITS, label_addr, split_addr};
+
+newtype!(XId, "x");
+
+impl TryVal for CharId {
+	fn desc() -> String { "'self', 'target', 'char'".to_owned() }
+
+	fn try_parse(p: &mut Parse) -> Result<Option<Self>> {
+		if let Some(()) = p.term("self")? {
+			Ok(Some(CharId(0xFF)))
+		} else if let Some(()) = p.term("target")? {
+			Ok(Some(CharId(0xFE)))
+		} else if let Some((n,)) = p.term("char")? {
+			Ok(Some(CharId(n)))
+		} else {
+			Ok(None)
+		}
+	}
+}
+
+// Labels are numbered, so that they can be parsed without any extra state. The numbers are
+// replaced with instruction indices once the whole file has been read. A label can be followed by
+// an offset, like `L3+1`, for addresses inside an instruction.
+impl TryVal for Addr {
+	fn desc() -> String { "label".to_owned() }
+
+	fn try_parse(p: &mut Parse) -> Result<Option<Self>> {
+		let Some(name) = test!(p, Token::Ident(a) if a.starts_with('L') => *a) else {
+			return Ok(None)
+		};
+		let Some(n) = name[1..].parse::<usize>().ok().filter(|n| *n <= usize::MAX >> OFFSET_BITS) else {
+			Diag::error(p.prev_span(), "labels must be named like 'L1'").emit();
+			return Err(Error)
+		};
+		let mut offset = 0;
+		if test!(p, Token::Plus) {
+			let S(s, o) = <S<u16>>::parse(p)?;
+			if o == 0 {
+				Diag::error(s, "label offset must not be zero").emit();
+			}
+			offset = o as usize;
+		}
+		Ok(Some(label_addr(n, offset)))
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+struct Code {
+	insns: Vec<(Span, Insn)>,
+	// label number → index of the following instruction
+	labels: BTreeMap<usize, S<usize>>,
+}
+
+#[derive(Default)]
+struct AniBuild {
+	chips: Many<ChipId, (FileId, FileId)>,
+	models: Vec<String>,
+	bones: One<(u8, Vec<String>)>,
+	sprite_offsets: One<[(u8, u8); 8]>,
+	funcs: Many<FuncDefId, S<Addr>>,
+	code: One<Code>,
+}
+
+pub fn parse(lines: &[Line], ctx: &Context) -> Result<Ani> {
+	let mut ani = AniBuild::default();
+	for line in lines {
+		let _ = Parse::new(line, ctx).parse_with(|p| parse_line(&mut ani, p));
+	}
+
+	if !ani.sprite_offsets.is_present() {
+		Diag::error(Span::new_at(0), "missing 'sprite_offsets'").emit();
+	}
+
+	let chips = ani.chips.get(|a| a.0 as usize);
+	let funcs = ani.funcs.get(|a| a.0 as usize);
+	let code = ani.code.get().unwrap_or_default();
+
+	// Instructions are placed far enough apart that offsets into them stay inside them
+	let resolve = |S(s, a): S<Addr>| {
+		let (n, offset) = split_addr(a);
+		match code.labels.get(&n) {
+			Some(S(_, i)) if *i < code.insns.len() => label_addr(*i, offset),
+			Some(S(l, _)) => {
+				Diag::error(*l, "label must be followed by an instruction").emit();
+				Addr(0)
+			}
+			None => {
+				Diag::error(s, format_args!("undefined label 'L{n}'")).emit();
+				Addr(0)
+			}
+		}
+	};
+
+	let funcs = funcs.into_iter().map(resolve).collect();
+	let insns = code.insns.iter().enumerate().map(|(i, (s, insn))| {
+		let mut insn = insn.clone();
+		insn.visit_addrs(|a| *a = resolve(S(*s, *a)));
+		(label_addr(i, 0), insn)
+	}).collect();
+
+	Ok(Ani {
+		chips,
+		models: ani.models,
+		bones: ani.bones.get(),
+		sprite_offsets: ani.sprite_offsets.get().ok_or(Error)?,
+		funcs,
+		insns,
+	})
+}
+
+fn parse_line(ani: &mut AniBuild, p: &mut Parse) -> Result<()> {
+	let Some(key) = test!(p, Token::Ident(a) => a) else {
+		Diag::error(p.next_span(), "expected word").emit();
+		p.pos = p.tokens.len();
+		return Err(Error);
+	};
+	if test!(p, Token::Bracket(_)) {
+		p.pos -= 2;
+	}
+	match *key {
+		"chip" => {
+			let S(s, n) = Val::parse(p)?;
+			ani.chips.mark(p.tokens[0].0 | s, n);
+			let v = Val::parse(p)?;
+			ani.chips.insert(n, v);
+		}
+		"model" => {
+			ani.models.push(Val::parse(p)?);
+		}
+		"bones" => {
+			ani.bones.mark(p.head_span());
+			ani.bones.set(Val::parse(p)?);
+		}
+		"sprite_offsets" => {
+			ani.sprite_offsets.mark(p.head_span());
+			let mut v = [(0, 0); 8];
+			for a in &mut v {
+				let Some(b) = p.tuple()? else {
+					Diag::error(p.next_span(), "expected (x, y)").emit();
+					return Err(Error)
+				};
+				*a = b;
+			}
+			ani.sprite_offsets.set(v);
+		}
+		"fn" => {
+			let S(s, n) = Val::parse(p)?;
+			ani.funcs.mark(p.tokens[0].0 | s, n);
+			let v = Val::parse(p)?;
+			ani.funcs.insert(n, v);
+		}
+		"code" => {
+			ani.code.mark(p.head_span());
+			let code = parse_code(p);
+			ani.code.set(code);
+		}
+		_ => {
+			Diag::error(p.tokens[0].0, "unknown declaration")
+				.note(p.tokens[0].0, "expected \
+					'chip', 'model', 'bones', 'sprite_offsets', 'fn', 'code'")
+				.emit();
+			p.pos = p.tokens.len();
+		}
+	}
+	Ok(())
+}
+
+fn parse_code(p: &mut Parse) -> Code {
+	let mut code = Code::default();
+	for l in p.body() {
+		Parse::new(l, p.context).parse_with(|p| {
+			if test!(p, Token::At) {
+				let Ok(S(s, a)) = Val::parse(p) else {
+					p.pos = p.tokens.len();
+					return
+				};
+				let (n, offset) = split_addr(a);
+				if offset != 0 {
+					Diag::error(s, "label definitions cannot have an offset").emit();
+				}
+				if let Some(S(prev, _)) = code.labels.insert(n, S(s, code.insns.len())) {
+					Diag::error(s, "duplicate label")
+						.note(prev, "previous here")
+						.emit();
+				}
+				return
+			}
+
+			let s = p.head_span();
+			match try_parse_insn(p) {
+				Ok(Some(i)) => code.insns.push((s, i)),
+				Ok(None) => {
+					Diag::error(p.next_span(), "unknown instruction").emit();
+					p.pos = p.tokens.len();
+				}
+				Err(Error) => p.pos = p.tokens.len(),
+			}
+		});
+	}
+	code
+}
+
+fn try_parse_insn(p: &mut Parse) -> Result<Option<Insn>> {
+	if p.pos == p.tokens.len() {
+		Diag::error(p.next_span(), "can't parse insn").emit();
+		return Err(Error)
+	}
+	macro run([$(($ident:ident $(($_n:ident $ty:ty))*))*]) {
+		match p.tokens[p.pos].1 {
+			$(Token::Ident(stringify!($ident)) => {
+				p.pos += 1;
+				let s = p.prev_span();
+				let i = Insn::$ident($(<$ty>::parse(p)?),*);
+				// Labels are only resolved later
+				let mut j = i.clone();
+				j.visit_addrs(|a| *a = Addr(0));
+				if let Err(e) = Insn::validate(p.context.game, &j) {
+					Diag::error(s, format!("invalid instruction: {}", e)).emit();
+				}
+				return Ok(Some(i))
+			})*
+			_ => return Ok(None)
+		}
+	}
+	themelios_ani::insn::introspect!(run);
+}
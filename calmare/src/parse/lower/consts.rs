@@ -0,0 +1,275 @@
+//! Named constants, declared at the top level as `const NAME = value` or `alias NAME = value`
+//! (the two are interchangeable). A constant can be used anywhere a value of the same type is
+//! expected, so `alias ESTELLE = name[0]` can be used as a character, but not as an item.
+//!
+//! Declarations can also be shared between files with `use "file.clm"`, where the file contains
+//! nothing but declarations. Files brought in with `include` can also contain declarations; see
+//! [`super::include`].
+use std::collections::BTreeMap;
+
+use super::*;
+use crate::parse::lex::lex;
+
+pub type Consts<'a> = BTreeMap<&'a str, Const<'a>>;
+
+/// Reads a file referred to by `use` or `include`.
+pub type Loader<'a> = &'a dyn Fn(&str) -> std::io::Result<String>;
+
+#[derive(Debug, Clone)]
+pub struct Const<'a> {
+	pub value: &'a [S<Token<'a>>],
+	pub origin: Origin,
+}
+
+#[derive(Debug, Clone)]
+pub enum Origin {
+	/// The name of the constant in this file.
+	Here(Span),
+	/// Defined in another file, brought in by the `use` at this span. The string describes where.
+	Used(S<String>),
+}
+
+impl Const<'_> {
+	fn note(&self, d: Diag, name: &str, s: Span) -> Diag {
+		match &self.origin {
+			Origin::Here(def) => d.note(*def, format_args!("'{name}' is defined here")),
+			Origin::Used(S(u, desc)) => d.note(s, format_args!("'{name}' is {desc}")).note(*u, "from this 'use'"),
+		}
+	}
+}
+
+impl<'a> Parse<'a> {
+	fn next_const(&self) -> Option<(S<&'a str>, &'a Const<'a>)> {
+		let S(s, Token::Ident(name)) = self.tokens.get(self.pos)? else { return None };
+		Some((S(*s, *name), self.context.consts.get(name)?))
+	}
+
+	/// If the next token is a constant, parses its value with `f` instead. If `f` does not match
+	/// the whole value, the constant is treated as not matching.
+	pub(super) fn expand<T>(&mut self, f: impl FnOnce(&mut Parse<'a>) -> Result<Option<T>>) -> Result<Option<T>> {
+		let Some((S(s, name), c)) = self.next_const() else {
+			return Ok(None)
+		};
+		let end = c.value.last().map_or(s, |a| a.0.at_end());
+		let mut p = Parse::new_inner(c.value, end, self.context);
+		let (v, diags) = diagnose(|| {
+			let v = f(&mut p)?;
+			Ok(v.filter(|_| p.pos == p.tokens.len()))
+		});
+		for d in diags {
+			// The spans may be in another file, so point at the use instead
+			c.note(Diag { text: S(s, d.text.1), notes: Vec::new(), ..d }, name, s).emit();
+		}
+		let v = v?;
+		if v.is_some() {
+			self.pos += 1;
+		}
+		Ok(v)
+	}
+
+	/// Adds a note about the next token if it is a constant, for when it was not accepted.
+	pub(super) fn note_const(&self, d: Diag) -> Diag {
+		match self.next_const() {
+			Some((S(s, name), c)) => c.note(d, name, s),
+			None => d,
+		}
+	}
+}
+
+fn keyword<'a>(line: &Line<'a>) -> Option<&'a str> {
+	match line.head.first() {
+		Some(S(_, Token::Ident(kw @ ("const" | "alias" | "use" | "include")))) => Some(kw),
+		_ => None,
+	}
+}
+
+/// A file brought in by `use` or `include`.
+#[derive(Debug, Clone)]
+pub struct UsedFile {
+	/// The `use` or `include` line, where diagnostics in the file are reported.
+	pub span: Span,
+	pub path: String,
+	pub src: String,
+	pub include: bool,
+}
+
+impl UsedFile {
+	pub fn line(&self, pos: usize) -> usize {
+		self.src.get(..pos).map_or(0, |a| a.matches('\n').count()) + 1
+	}
+}
+
+/// Separates the declarations from the rest of the file, and loads the files mentioned in `use`
+/// and `include`.
+pub fn declarations<'a>(lines: &'a [Line<'a>], load: Loader) -> (Vec<&'a Line<'a>>, Vec<Line<'a>>, Vec<UsedFile>) {
+	let (decls, rest): (Vec<&Line>, Vec<&Line>) = lines.iter().partition(|a| keyword(a).is_some());
+	let mut files = Vec::new();
+	for line in &decls {
+		let kw = keyword(line).unwrap();
+		if !matches!(kw, "use" | "include") {
+			continue
+		}
+		let [S(s, _), S(s2, Token::String(path))] = line.head.as_slice() else {
+			Diag::error(line.head_span(), format_args!("expected '{kw} \"file\"'")).emit();
+			continue
+		};
+		if line.body.is_some() {
+			Diag::error(line.eol, "body not expected here").emit();
+		}
+		match load(path) {
+			Ok(src) => files.push(UsedFile { span: *s | *s2, path: path.clone(), src, include: kw == "include" }),
+			Err(e) => Diag::error(*s | *s2, format_args!("could not read '{path}': {e}")).emit(),
+		}
+	}
+	(decls, rest.into_iter().cloned().collect(), files)
+}
+
+/// Runs `f` on a used file, reporting its diagnostics at the `use`, since they are not in this file.
+pub(super) fn in_file<T>(file: &UsedFile, f: impl FnOnce() -> T) -> T {
+	let (v, diags) = diagnose(f);
+	for d in diags {
+		let line = file.line(d.text.0.start);
+		let mut d2 = Diag::new(d.level, file.span, format_args!("{}, line {line}: {}", file.path, d.text.1));
+		for S(s, note) in d.notes {
+			d2 = d2.note(file.span, format_args!("line {}: {note}", file.line(s.start)));
+		}
+		d2.emit();
+	}
+	v
+}
+
+pub fn lex_used(files: &[UsedFile]) -> Vec<Vec<Line<'_>>> {
+	files.iter().map(|f| in_file(f, || lex(&f.src))).collect()
+}
+
+/// Collects the constants from the main file's declarations and function names, and the lexed
+/// `use`d files and their function names.
+pub fn collect<'a>(
+	decls: &[&'a Line<'a>],
+	funcs: &'a [super::names::FuncName<'a>],
+	files: &[UsedFile],
+	used: &'a [Vec<Line<'a>>],
+	used_funcs: &'a [Vec<super::names::FuncName<'a>>],
+) -> Consts<'a> {
+	let mut consts = Consts::new();
+
+	for ((file, lines), funcs) in files.iter().zip(used).zip(used_funcs) {
+		let origin = |name: Span| {
+			Origin::Used(S(file.span, format!("defined in {}, line {}", file.path, file.line(name.start))))
+		};
+		in_file(file, || {
+			for (name, value) in funcs {
+				insert(&mut consts, *name, value, origin);
+			}
+			for line in lines {
+				match keyword(line) {
+					Some(kw @ ("use" | "include")) => {
+						Diag::error(line.head_span(), format_args!("'{kw}' is not supported in other files")).emit();
+					}
+					Some(_) => declare(&mut consts, line, origin),
+					// Functions are handled by the scena
+					None if file.include && super::include::is_func(line) => {}
+					None if file.include => {
+						Diag::error(line.head_span(), "expected 'const', 'alias', or 'fn'").emit();
+					}
+					None => Diag::error(line.head_span(), "expected 'const' or 'alias'").emit(),
+				}
+			}
+		});
+	}
+
+	for line in decls {
+		if matches!(keyword(line), Some("const" | "alias")) {
+			declare(&mut consts, line, Origin::Here);
+		}
+	}
+	for (name, value) in funcs {
+		insert(&mut consts, *name, value, Origin::Here);
+	}
+
+	// Constants referring to other constants could be recursive, so just forbid that
+	let mut bad = Vec::new();
+	for (name, c) in &consts {
+		if let Some(S(s, other)) = find_const(&consts, c.value) {
+			let d = match &c.origin {
+				Origin::Here(_) => Diag::error(s, "constants cannot refer to other constants"),
+				Origin::Used(S(u, desc)) => Diag::error(*u, format_args!("'{name}', {desc}, refers to another constant '{other}'")),
+			};
+			d.emit();
+			bad.push(*name);
+		}
+	}
+	for name in bad {
+		consts.remove(name);
+	}
+	consts
+}
+
+fn declare<'a>(consts: &mut Consts<'a>, line: &'a Line<'a>, origin: impl FnOnce(Span) -> Origin) {
+	if line.body.is_some() {
+		Diag::error(line.eol, "body not expected here").emit();
+	}
+	let [S(_, Token::Ident(kw)), S(s, Token::Ident(name)), S(_, Token::Eq), value@..] = line.head.as_slice() else {
+		let kw = match line.head.first() { Some(S(_, Token::Ident(kw))) => *kw, _ => "const" };
+		Diag::error(line.head_span(), format_args!("expected '{kw} NAME = value'")).emit();
+		return
+	};
+	if value.is_empty() {
+		Diag::error(line.eol, format_args!("'{kw}' needs a value")).emit();
+		return
+	}
+	insert(consts, S(*s, *name), value, origin);
+}
+
+fn insert<'a>(consts: &mut Consts<'a>, S(s, name): S<&'a str>, value: &'a [S<Token<'a>>], origin: impl FnOnce(Span) -> Origin) {
+	if let Some(prev) = consts.get(name) {
+		prev.note(Diag::error(s, format_args!("'{name}' is already defined")), name, s).emit();
+		return
+	}
+	consts.insert(name, Const { value, origin: origin(s) });
+}
+
+fn find_const<'a>(consts: &Consts, tokens: &[S<Token<'a>>]) -> Option<S<&'a str>> {
+	tokens.iter().find_map(|S(s, t)| match t {
+		Token::Ident(a) if consts.contains_key(a) => Some(S(*s, *a)),
+		Token::Paren(d) | Token::Bracket(d) => find_const(consts, &d.tokens),
+		_ => None,
+	})
+}
+
+#[test]
+fn test() {
+	let src = "\
+calmare fc scena
+use \"names.clm\"
+const TEAR = item[200]
+scena:
+	name \"a\" \"b\"
+	town town[0]
+	bgm bgm[0]
+	item_use fn[0,0]
+fn[0]:
+	ItemAdd TEAR 1
+	ItemAdd ESTELLE 1
+	TextTalk ESTELLE { {item TEAR} }
+";
+	let load = |path: &str| {
+		assert_eq!(path, "names.clm");
+		Ok("alias ESTELLE = name[0]\n".to_owned())
+	};
+	let (_, diags) = crate::parse_with(src, None, &load);
+	assert_eq!(diags.len(), 1, "{diags:?}");
+	assert_eq!(&src[diags[0].text.0.as_range()], "ESTELLE");
+	assert_eq!(diags[0].text.1, "expected 'item'");
+
+	let src = src.replace("\tItemAdd ESTELLE 1\n", "");
+	let (v, diags) = crate::parse_with(&src, None, &load);
+	assert!(diags.is_empty(), "{diags:?}");
+	let Some((_, crate::Content::ED6Scena(scena))) = v else { panic!() };
+	let expected = crate::parse(&src.replace("use \"names.clm\"\n", "")
+		.replace("const TEAR = item[200]\n", "")
+		.replace("TEAR", "item[200]")
+		.replace("ESTELLE", "name[0]"), None).0;
+	let Some((_, crate::Content::ED6Scena(expected))) = expected else { panic!() };
+	assert_eq!(format!("{scena:?}"), format!("{expected:?}"));
+}
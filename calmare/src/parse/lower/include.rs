@@ -0,0 +1,207 @@
+//! Sharing functions between scenas with `include "file.clm"`.
+//!
+//! The included file contains functions numbered from `fn[0]`, and possibly constants. The
+//! functions are placed after the scena's own, and every `fn[0, n]` in the file, including in its
+//! constants, is renumbered to match. References to other scenas, such as `fn[1, n]`, are left
+//! alone. The scena can then call the functions through the constants.
+//!
+//! Functions in included files can also be named, like in the scena itself. The names are given
+//! indices within the file before renumbering, and are then usable as constants in the scena.
+use super::*;
+use super::consts::{UsedFile, in_file};
+use super::names::FuncName;
+
+/// The function definitions from each included file.
+pub type Included<'a> = Vec<(&'a UsedFile, Vec<&'a Line<'a>>)>;
+
+/// Whether the line is a function definition, `fn[n]:`.
+pub(super) fn is_func(line: &Line) -> bool {
+	matches!(line.head.as_slice(), [S(_, Token::Ident("fn")), S(_, Token::Bracket(_))])
+}
+
+fn func_index(line: &Line) -> Option<u64> {
+	match line.head.as_slice() {
+		[S(_, Token::Ident("fn")), S(_, Token::Bracket(d))] => match d.tokens.as_slice() {
+			[S(_, Token::Int(n))] => Some(*n),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// Gives indices to the named functions in the included files, as [`super::names::assign`] does
+/// for the scena. Only scenas have functions, so other files have no names.
+pub fn names<'a>(ty: FileType, files: &[UsedFile], lexed: &mut [Vec<Line<'a>>]) -> Vec<Vec<FuncName<'a>>> {
+	files.iter().zip(lexed).map(|(file, lines)| {
+		if file.include && ty == FileType::Scena {
+			names::assign(lines)
+		} else {
+			Vec::new()
+		}
+	}).collect()
+}
+
+/// Renumbers the functions in the included files, placing them after the functions in `lines`
+/// and each other, and updates the files' function names to match. Functions from files that
+/// could not be renumbered are removed, so that they do not cause confusing errors later.
+pub fn renumber(lines: &[Line], files: &[UsedFile], lexed: &mut [Vec<Line>], names: &mut [Vec<FuncName>]) {
+	let mut base = lines.iter().filter_map(func_index).map(|n| n + 1).max().unwrap_or(0);
+	for ((file, lines), names) in files.iter().zip(lexed).zip(names) {
+		if !file.include {
+			continue
+		}
+		let ok = in_file(file, || {
+			let mut ok = true;
+			for (n, line) in lines.iter().filter(|a| is_func(a)).enumerate() {
+				if func_index(line) != Some(n as u64) {
+					Diag::error(line.head_span(), format_args!("expected fn[{n}]"))
+						.note(line.head_span(), "functions in included files must be numbered in order from zero")
+						.emit();
+					ok = false;
+				}
+			}
+			for line in lines.iter() {
+				ok &= check_lines(line);
+			}
+			ok
+		});
+		if !ok {
+			lines.retain(|a| !is_func(a));
+			names.clear();
+			continue
+		}
+
+		let mut count = 0;
+		for line in lines.iter_mut() {
+			if is_func(line) {
+				count += 1;
+				if let Token::Bracket(d) = &mut line.head[1].1 && let [S(_, Token::Int(n))] = d.tokens.as_mut_slice() {
+					*n += base;
+				}
+			}
+			shift_line(line, base);
+		}
+		for (_, value) in names.iter_mut() {
+			shift(value, base);
+		}
+		base += count;
+	}
+}
+
+fn check_lines(line: &Line) -> bool {
+	let mut ok = check_refs(&line.head);
+	for line in line.body.iter().flatten() {
+		ok &= check_lines(line);
+	}
+	ok
+}
+
+/// Checks that all references to this scena's functions are written in a way that can be
+/// renumbered.
+fn check_refs(tokens: &[S<Token>]) -> bool {
+	let mut ok = true;
+	for (i, S(s, t)) in tokens.iter().enumerate() {
+		match t {
+			Token::Bracket(d) if i > 0 && let S(s0, Token::Ident("fn")) = &tokens[i-1] && s0.connects(*s) => {
+				match d.tokens.as_slice() {
+					[S(_, Token::Int(0)), S(_, Token::Comma), S(_, Token::Int(_))] => {}
+					[S(_, Token::Int(k)), ..] if *k != 0 => {}
+					// Function definitions
+					[S(_, Token::Int(_))] => {}
+					_ => {
+						Diag::error(*s0 | *s, "cannot renumber this")
+							.note(*s0 | *s, "function references in included files must be written as 'fn[0, n]'")
+							.emit();
+						ok = false;
+					}
+				}
+			}
+			Token::Paren(d) | Token::Bracket(d) => ok &= check_refs(&d.tokens),
+			_ => {}
+		}
+	}
+	ok
+}
+
+fn shift_line(line: &mut Line, base: u64) {
+	shift(&mut line.head, base);
+	for line in line.body.iter_mut().flatten() {
+		shift_line(line, base);
+	}
+}
+
+fn shift(tokens: &mut [S<Token>], base: u64) {
+	for i in 0..tokens.len() {
+		let is_ref = i > 0 && matches!(tokens[i-1], S(s0, Token::Ident("fn")) if s0.connects(tokens[i].0));
+		match &mut tokens[i].1 {
+			Token::Bracket(d) if is_ref => {
+				if let [S(_, Token::Int(0)), S(_, Token::Comma), S(_, Token::Int(n))] = d.tokens.as_mut_slice() {
+					*n += base;
+				}
+			}
+			Token::Paren(d) | Token::Bracket(d) => shift(&mut d.tokens, base),
+			_ => {}
+		}
+	}
+}
+
+/// Collects the function definitions of the included files. Only scenas have functions.
+pub fn functions<'a>(ty: FileType, files: &'a [UsedFile], lexed: &'a [Vec<Line<'a>>]) -> Included<'a> {
+	let mut out = Vec::new();
+	for (file, lines) in files.iter().zip(lexed) {
+		if !file.include {
+			continue
+		}
+		if ty != FileType::Scena {
+			Diag::error(file.span, "'include' can only be used in scenas").emit();
+			continue
+		}
+		out.push((file, lines.iter().filter(|a| is_func(a)).collect()));
+	}
+	out
+}
+
+#[test]
+fn test() {
+	let src = "\
+calmare fc scena
+include \"common.clm\"
+scena:
+	name \"a\" \"b\"
+	town town[0]
+	bgm bgm[0]
+	item_use GREET
+fn[0]:
+	Call GREET
+	Call helper
+";
+	let load = |_: &str| Ok("\
+const GREET = fn[0, 1]
+fn[0]:
+	Call fn[1, 0]
+fn[1]:
+	Call fn[0, 0]
+fn helper:
+	Call GREET
+".to_owned());
+	let (v, diags) = crate::parse_with(src, None, &load);
+	assert!(diags.is_empty(), "{diags:?}");
+	let expected = crate::parse("\
+calmare fc scena
+scena:
+	name \"a\" \"b\"
+	town town[0]
+	bgm bgm[0]
+	item_use fn[0, 2]
+fn[0]:
+	Call fn[0, 2]
+	Call fn[0, 3]
+fn[1]:
+	Call fn[1, 0]
+fn[2]:
+	Call fn[0, 1]
+fn[3]:
+	Call fn[0, 2]
+", None).0;
+	assert_eq!(format!("{v:?}"), format!("{expected:?}"));
+}
@@ -0,0 +1,105 @@
+//! Named functions, `fn talk_to_mayor:`, which can then be referred to as `talk_to_mayor`
+//! instead of `fn[0, n]`.
+//!
+//! Named functions are given the lowest indices not used by any other function, in order. If the
+//! index matters, for example because another scena calls the function, it can be pinned with
+//! `fn[3] talk_to_mayor:`.
+use std::collections::BTreeSet;
+
+use super::*;
+use crate::parse::lex::Delimited;
+
+/// A function name, and the `fn[0, n]` it stands for.
+pub type FuncName<'a> = (S<&'a str>, Vec<S<Token<'a>>>);
+
+/// Gives indices to the named functions, and rewrites them to the plain `fn[n]:` form.
+pub fn assign<'a>(lines: &mut [Line<'a>]) -> Vec<FuncName<'a>> {
+	let pinned = lines.iter().filter_map(|line| match line.head.as_slice() {
+		[S(_, Token::Ident("fn")), S(_, Token::Bracket(d)), ..] => match d.tokens.as_slice() {
+			[S(_, Token::Int(n))] => Some(*n),
+			_ => None,
+		},
+		_ => None,
+	}).collect::<BTreeSet<_>>();
+
+	let mut next = 0;
+	let mut out = Vec::new();
+	for line in lines {
+		let (name, n) = match line.head.as_slice() {
+			&[S(s0, Token::Ident("fn")), S(s, Token::Ident(name))] => {
+				while pinned.contains(&next) {
+					next += 1;
+				}
+				next += 1;
+				// Spanned so that it looks like `fn[n]` to the parser
+				line.head[1] = S(Span::new(s0.end, s.end), Token::Bracket(Delimited {
+					open: Span::new(s0.end, s.start),
+					tokens: vec![S(s, Token::Int(next - 1))],
+					close: s.at_end(),
+				}));
+				(S(s, name), next - 1)
+			}
+			[S(_, Token::Ident("fn")), S(_, Token::Bracket(d)), S(s, Token::Ident(name))]
+				if let [S(_, Token::Int(n))] = d.tokens.as_slice() =>
+			{
+				let v = (S(*s, *name), *n);
+				line.head.truncate(2);
+				v
+			}
+			_ => continue,
+		};
+		let s = name.0;
+		out.push((name, vec![
+			S(s.at_start(), Token::Ident("fn")),
+			S(s, Token::Bracket(Delimited {
+				open: s.at_start(),
+				tokens: vec![S(s, Token::Int(0)), S(s, Token::Comma), S(s, Token::Int(n))],
+				close: s.at_end(),
+			})),
+		]));
+	}
+	out
+}
+
+#[test]
+fn test() {
+	let src = "\
+calmare fc scena
+scena:
+	name \"a\" \"b\"
+	town town[0]
+	bgm bgm[0]
+	item_use init
+fn init:
+	Call fn[1, 0]
+fn[1] talk:
+	Call leave
+fn leave:
+	Call init
+";
+	let (v, diags) = crate::parse(src, None);
+	assert!(diags.is_empty(), "{diags:?}");
+	let (game, v) = v.unwrap();
+	let expected = crate::parse("\
+calmare fc scena
+scena:
+	name \"a\" \"b\"
+	town town[0]
+	bgm bgm[0]
+	item_use fn[0,0]
+fn[0]:
+	Call fn[1,0]
+fn[1]:
+	Call fn[0,2]
+fn[2]:
+	Call fn[0,0]
+", None).0;
+	assert_eq!(format!("{v:?}"), format!("{:?}", expected.unwrap().1));
+
+	let names = crate::parse_symbols("fn[0] init\nfn[1] talk // pinned\nfn[2] leave\n").unwrap();
+	let out = crate::to_string_with_names(game, &v, None, &names);
+	assert!(out.contains("\nfn[1] talk:\n\tCall leave\n"), "{out}");
+	let (v2, diags) = crate::parse(&out, None);
+	assert!(diags.is_empty(), "{diags:?}");
+	assert_eq!(format!("{:?}", v2.unwrap().1), format!("{v:?}"));
+}
@@ -8,7 +8,7 @@ pub mod ed6;
 pub mod ed7;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct FuncDefId(u16);
+pub(super) struct FuncDefId(pub(super) u16);
 newtype!(FuncDefId, "fn");
 
 #[derive(Debug, Clone)]
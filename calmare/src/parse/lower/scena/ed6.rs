@@ -23,11 +23,16 @@ struct ScenaBuild {
 	functions: Many<FuncDefId, Code>,
 }
 
-pub fn parse(lines: &[Line], ctx: &Context) -> Result<Scena> {
+pub fn parse(lines: &[Line], included: &include::Included, ctx: &Context) -> Result<Scena> {
 	let mut scena = ScenaBuild::default();
 	for line in lines {
 		let _ = Parse::new(line, ctx).parse_with(|p| parse_line(&mut scena, p));
 	}
+	for (file, lines) in included {
+		consts::in_file(file, || for line in lines {
+			let _ = Parse::new(line, ctx).parse_with(|p| parse_line(&mut scena, p));
+		});
+	}
 
 	if !scena.header.is_present() {
 		Diag::error(Span::new_at(0), "missing 'scena' block").emit();
@@ -0,0 +1,218 @@
+use themelios::tables::{name::*, quest::*, town::*, world::*, bgm::*, se::*, ent::*};
+
+use super::*;
+use crate::Content;
+
+pub fn parse(lines: &[Line], ctx: &Context) -> Result<Content> {
+	let ed7 = ctx.game.is_ed7();
+	Ok(match ctx.ty {
+		FileType::Name if ed7 => Content::ED7Name(parse_ed7_name(lines, ctx)),
+		FileType::Name => {
+			let (t1, t2) = parse_ed6_name(lines, ctx);
+			Content::ED6Name(t1, t2)
+		}
+		FileType::Quest if ed7 => Content::ED7Quest(parse_ed7_quest(lines, ctx)),
+		FileType::Quest => Content::ED6Quest(parse_ed6_quest(lines, ctx)),
+		FileType::Town => Content::Town(parse_town(lines, ctx)),
+		FileType::World => Content::ED6World(parse_ed6_world(lines, ctx)),
+		FileType::Bgm => Content::ED7Bgm(parse_ed7_bgm(lines, ctx)),
+		FileType::Sound => Content::ED7Sound(parse_ed7_sound(lines, ctx)),
+		FileType::Ent => Content::ED6Ent(parse_ed6_ent(lines, ctx)),
+		FileType::Scena | FileType::Ani => unreachable!(),
+	})
+}
+
+fn each_line(lines: &[Line], ctx: &Context, key: &str, mut f: impl FnMut(&mut Parse) -> Result<()>) {
+	for line in lines {
+		let _ = Parse::new(line, ctx).parse_with(|p| {
+			if !p.word(key) {
+				Diag::error(p.next_span(), "unknown declaration")
+					.note(p.next_span(), format_args!("expected '{key}'"))
+					.emit();
+				p.pos = p.tokens.len();
+				return Err(Error);
+			}
+			if test!(p, Token::Bracket(_)) {
+				p.pos -= 2;
+			}
+			let v = f(p);
+			if v.is_err() {
+				p.pos = p.tokens.len();
+			}
+			v
+		});
+	}
+}
+
+fn parse_ed6_name(lines: &[Line], ctx: &Context) -> (Vec<ED6Name>, Vec<ED6Name>) {
+	let is_fc = ctx.game.base() == BaseGame::Fc;
+	let mut t1 = Vec::new();
+	let mut t2 = Vec::new();
+	each_line(lines, ctx, "name", |p| {
+		let S(s, id) = Val::parse(p)?;
+		let mut stch = One::default();
+		parse_data!(p => {
+			name, chip1, chip2, ms1, ms2,
+			stch => |p: &mut Parse| {
+				stch.mark(p.prev_span());
+				stch.set(Val::parse(p)?);
+				Ok(())
+			}
+		});
+		let stch = match (is_fc, stch.is_present()) {
+			(true, false) => FileId::NONE,
+			(false, true) => stch.get().ok_or(Error)?,
+			(true, true) => {
+				Diag::error(p.head_span(), "'stch' does not exist in FC").emit();
+				return Err(Error)
+			}
+			(false, false) => {
+				Diag::error(p.head_span(), "missing fields")
+					.note(p.head_span(), "'stch'")
+					.emit();
+				return Err(Error)
+			}
+		};
+		let n = ED6Name { id, name, chip1, chip2, ms1, ms2, stch };
+		// 999 and 2999 mark the end of each half, so they can't be used
+		match id.0 {
+			0..=998 => t1.push(n),
+			1000..=2998 if !is_fc => t2.push(n),
+			_ => {
+				Diag::error(s, "name id out of range")
+					.note(s, if is_fc { "must be below 999" } else { "must be below 999, or between 1000 and 2998" })
+					.emit();
+			}
+		}
+		Ok(())
+	});
+	(t1, t2)
+}
+
+fn parse_ed7_name(lines: &[Line], ctx: &Context) -> Vec<ED7Name> {
+	let mut table = Vec::new();
+	each_line(lines, ctx, "name", |p| {
+		let id = Val::parse(p)?;
+		parse_data!(p => {
+			name, chip1, chip2, ms1, ms2,
+		});
+		table.push(ED7Name { id, name, chip1, chip2, ms1, ms2 });
+		Ok(())
+	});
+	table
+}
+
+fn parse_ed6_quest(lines: &[Line], ctx: &Context) -> Vec<ED6Quest> {
+	let mut table = Vec::new();
+	each_line(lines, ctx, "quest", |p| {
+		let id = Val::parse(p)?;
+		let mut steps = Vec::new();
+		parse_data!(p => {
+			section, index, bp, mira, flags, name, desc,
+			step => |p: &mut Parse| {
+				steps.push(Val::parse(p)?);
+				Ok(())
+			}
+		});
+		if steps.len() != 16 {
+			Diag::error(p.head_span(), "wrong number of steps")
+				.note(p.head_span(), format_args!("expected 16, found {}", steps.len()))
+				.emit();
+		}
+		table.push(ED6Quest { id, section, index, bp, mira, flags, name, desc, steps });
+		Ok(())
+	});
+	table
+}
+
+fn parse_ed7_quest(lines: &[Line], ctx: &Context) -> Vec<ED7Quest> {
+	let mut table = Vec::new();
+	each_line(lines, ctx, "quest", |p| {
+		let id = Val::parse(p)?;
+		let mut steps = Vec::new();
+		parse_data!(p => {
+			section, mira, bp, unk1, flags, name, client, desc,
+			step => |p: &mut Parse| {
+				steps.push(Val::parse(p)?);
+				Ok(())
+			}
+		});
+		table.push(ED7Quest { id, section, mira, bp, unk1, flags, name, client, desc, steps });
+		Ok(())
+	});
+	table
+}
+
+fn parse_town(lines: &[Line], ctx: &Context) -> Vec<Town> {
+	// The order of the strings is not necessarily the same as the ids, so keep them as written
+	let mut ids = Many::<TownId, ()>::default();
+	let mut table = Vec::new();
+	each_line(lines, ctx, "town", |p| {
+		let (S(s, id), name, kind) = Val::parse(p)?;
+		ids.mark(s, id);
+		ids.insert(id, ());
+		table.push(Town { id, name, kind });
+		Ok(())
+	});
+	ids.get(|a| a.0 as usize);
+	table
+}
+
+fn parse_ed6_world(lines: &[Line], ctx: &Context) -> Vec<ED6World> {
+	let mut table = Vec::new();
+	each_line(lines, ctx, "scena", |p| {
+		let (scena, pos) = Val::parse(p)?;
+		table.push(ED6World { scena, pos });
+		Ok(())
+	});
+	table
+}
+
+fn parse_ed7_bgm(lines: &[Line], ctx: &Context) -> Vec<ED7Bgm> {
+	let mut table = Vec::new();
+	each_line(lines, ctx, "bgm", |p| {
+		let id = Val::parse(p)?;
+		parse_data!(p => {
+			file_num, loop_start, loop_end, loops,
+		});
+		table.push(ED7Bgm { loop_start, loop_end, file_num, id, loops });
+		Ok(())
+	});
+	table
+}
+
+fn parse_ed7_sound(lines: &[Line], ctx: &Context) -> Vec<ED7Sound> {
+	let mut ids = Many::<SoundId, ()>::default();
+	let mut table = Vec::new();
+	each_line(lines, ctx, "sound", |p| {
+		let (S(s, id), file_num, unk1, unk2) = Val::parse(p)?;
+		ids.mark(s, id);
+		ids.insert(id, ());
+		table.push(ED7Sound { id, file_num, unk1, unk2 });
+		Ok(())
+	});
+	table
+}
+
+fn parse_ed6_ent(lines: &[Line], ctx: &Context) -> Vec<ED6Ent> {
+	let mut table = Many::<EntranceId, ED6Ent>::default();
+	each_line(lines, ctx, "entrance", |p| {
+		let S(s, n) = Val::parse(p)?;
+		table.mark(p.tokens[0].0 | s, n);
+		parse_data!(p => {
+			name, bbox, pos, angle, unk1, flags, unk2,
+			dest_name, dest, unk3,
+			cam_from, cam_at, cam_zoom, cam_pers, cam_deg, cam_limit,
+			town, unk4,
+		});
+		let (dest, dest_entrance) = dest;
+		table.insert(n, ED6Ent {
+			name, bbox, pos, angle, unk1, flags, unk2,
+			dest_name, dest, dest_entrance, unk3,
+			cam_from, cam_deg, cam_zoom, cam_pers, cam_at, cam_limit,
+			town, unk4,
+		});
+		Ok(())
+	});
+	table.get(|a| a.0 as usize)
+}
@@ -1,6 +1,7 @@
 pub mod diag;
 pub mod lex;
 pub mod lower;
+pub mod fmt;
 
 pub use diag::Diag;
 use themelios::types::Game;
@@ -9,7 +10,7 @@ use themelios::lookup::Lookup;
 pub fn compile(src: &str, lookup: Option<&dyn Lookup>) -> (Option<(Game, crate::Content)>, Vec<Diag>) {
 	let (v, diag) = diag::diagnose(|| {
 		let tok = lex::lex(src);
-		lower::parse(&tok, lookup)
+		lower::parse(&tok, lookup, &|_| Err(std::io::ErrorKind::Unsupported.into()))
 	});
 	if diag.iter().any(|a| a.is_fatal()) {
 		(None, diag)
@@ -0,0 +1,273 @@
+use themelios::tables::{name::*, quest::*, town::*, world::*, bgm::*, se::*, ent::*};
+use themelios::types::*;
+use crate::writer::Context;
+use crate::common::{self, ContextExt};
+
+fn header(f: &mut Context, ty: &str) {
+	let g = common::game(f.game);
+	f.kw("calmare").kw(g).kw(ty).line();
+	f.line();
+}
+
+pub fn write_ed6_name(f: &mut Context, t1: &[ED6Name], t2: &[ED6Name]) {
+	header(f, "name");
+	// The two halves are told apart by their ids: the first ends at 999, the second at 2999.
+	for name in t1.iter().chain(t2) {
+		f.val(&name.id).suf(":").line().indent(|f| {
+			f.kw("name").val(&name.name).line();
+			f.kw("chip1").val(&name.chip1.0).val(&name.chip1.1).line();
+			f.kw("chip2").val(&name.chip2.0).val(&name.chip2.1).line();
+			f.kw("ms1").val(&name.ms1).line();
+			f.kw("ms2").val(&name.ms2).line();
+			if f.game.base() != BaseGame::Fc {
+				f.kw("stch").val(&name.stch).line();
+			}
+		});
+		f.line();
+	}
+}
+
+pub fn write_ed7_name(f: &mut Context, table: &[ED7Name]) {
+	header(f, "name");
+	for name in table {
+		f.val(&name.id).suf(":").line().indent(|f| {
+			f.kw("name").val(&name.name).line();
+			f.kw("chip1").val(&name.chip1).line();
+			f.kw("chip2").val(&name.chip2).line();
+			f.kw("ms1").val(&name.ms1).line();
+			f.kw("ms2").val(&name.ms2).line();
+		});
+		f.line();
+	}
+}
+
+pub fn write_ed6_quest(f: &mut Context, table: &[ED6Quest]) {
+	header(f, "quest");
+	for quest in table {
+		f.val(&quest.id).suf(":").line().indent(|f| {
+			f.kw("section").val(&quest.section).line();
+			f.kw("index").val(&quest.index).line();
+			f.kw("bp").val(&quest.bp).line();
+			f.kw("mira").val(&quest.mira).line();
+			f.kw("flags").val(&quest.flags).line();
+			f.kw("name").val(&quest.name).line();
+			f.kw("desc").val(&quest.desc).line();
+			for step in &quest.steps {
+				f.kw("step").val(step).line();
+			}
+		});
+		f.line();
+	}
+}
+
+pub fn write_ed7_quest(f: &mut Context, table: &[ED7Quest]) {
+	header(f, "quest");
+	for quest in table {
+		f.val(&quest.id).suf(":").line().indent(|f| {
+			f.kw("section").val(&quest.section).line();
+			f.kw("mira").val(&quest.mira).line();
+			f.kw("bp").val(&quest.bp).line();
+			f.kw("unk1").val(&quest.unk1).line();
+			f.kw("flags").val(&quest.flags).line();
+			f.kw("name").val(&quest.name).line();
+			f.kw("client").val(&quest.client).line();
+			f.kw("desc").val(&quest.desc).line();
+			for step in &quest.steps {
+				f.kw("step").val(step).line();
+			}
+		});
+		f.line();
+	}
+}
+
+pub fn write_town(f: &mut Context, table: &[Town]) {
+	header(f, "town");
+	for town in table {
+		f.val(&town.id).val(&town.name).val(&town.kind).line();
+	}
+}
+
+pub fn write_ed6_world(f: &mut Context, table: &[ED6World]) {
+	header(f, "world");
+	for world in table {
+		f.kw("scena").val(&world.scena).val(&world.pos).line();
+	}
+}
+
+pub fn write_ed7_bgm(f: &mut Context, table: &[ED7Bgm]) {
+	header(f, "bgm");
+	for bgm in table {
+		f.val(&bgm.id).suf(":").line().indent(|f| {
+			f.kw("file_num").val(&bgm.file_num).line();
+			f.kw("loop_start").val(&bgm.loop_start).line();
+			f.kw("loop_end").val(&bgm.loop_end).line();
+			f.kw("loops").val(&bgm.loops).line();
+		});
+		f.line();
+	}
+}
+
+pub fn write_ed7_sound(f: &mut Context, table: &[ED7Sound]) {
+	header(f, "sound");
+	for se in table {
+		f.val(&se.id).val(&se.file_num).val(&se.unk1).val(&se.unk2).line();
+	}
+}
+
+pub fn write_ed6_ent(f: &mut Context, table: &[ED6Ent]) {
+	header(f, "ent");
+	for (i, ent) in table.iter().enumerate() {
+		f.val(&EntranceId(i as u8)).suf(":").line().indent(|f| {
+			f.kw("name").val(&ent.name).line();
+			f.kw("bbox").val(&ent.bbox.0).val(&ent.bbox.1).line();
+			f.kw("pos").val(&ent.pos).line();
+			f.kw("angle").val(&ent.angle).line();
+			f.kw("unk1").val(&ent.unk1).line();
+			f.kw("flags").val(&ent.flags).line();
+			f.kw("unk2").val(&ent.unk2).line();
+			f.kw("dest_name").val(&ent.dest_name).line();
+			f.kw("dest").val(&ent.dest).val(&ent.dest_entrance).line();
+			f.kw("unk3").val(&ent.unk3).line();
+			f.kw("cam_from").val(&ent.cam_from).line();
+			f.kw("cam_at").val(&ent.cam_at).line();
+			f.kw("cam_zoom").val(&ent.cam_zoom).line();
+			f.kw("cam_pers").val(&ent.cam_pers).line();
+			f.kw("cam_deg").val(&ent.cam_deg).line();
+			f.kw("cam_limit").val(&ent.cam_limit.0).val(&ent.cam_limit.1).line();
+			f.kw("town").val(&ent.town).line();
+			f.kw("unk4").val(&ent.unk4).line();
+		});
+		f.line();
+	}
+}
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	use themelios::text::{Text, TextSegment};
+	use crate::Content;
+	use glam::{IVec2, Vec3};
+
+	let text = |s: &str| Text(vec![vec![TextSegment::String(s.to_owned()), TextSegment::Line, TextSegment::Color(2)]]);
+	let ed6_name = |id| ED6Name {
+		id: NameId(id),
+		name: TString("Estelle".to_owned()),
+		chip1: (FileId(0x00060000), FileId(0x00060001)),
+		chip2: (FileId(0x00060002), FileId(0x00060003)),
+		ms1: FileId(0x00070000),
+		ms2: FileId(0x00070001),
+		stch: FileId(0x00080000),
+	};
+	let cases = [
+		(Game::Sc, Content::ED6Name(vec![ed6_name(0), ed6_name(1)], vec![ed6_name(1001)])),
+		(Game::Zero, Content::ED7Name(vec![ED7Name {
+			id: NameId(0),
+			name: TString("Lloyd".to_owned()),
+			chip1: FileId(0x00700000),
+			chip2: FileId(0x00700001),
+			ms1: FileId(0),
+			ms2: FileId(0),
+		}])),
+		(Game::Fc, Content::ED6Quest(vec![ED6Quest {
+			id: QuestId(3),
+			section: 1,
+			index: 2,
+			bp: 3,
+			mira: 500,
+			flags: [Flag(10), Flag(11), Flag(12)],
+			name: TString("Lost Kitten".to_owned()),
+			desc: text("Find it"),
+			// Always 16 steps
+			steps: (0..16).map(|i| text(&format!("Step {i}"))).collect(),
+		}])),
+		// Ends with quest 255
+		(Game::Ao, Content::ED7Quest([4, 255].map(|id| ED7Quest {
+			id: QuestId(id),
+			section: 1,
+			mira: 500,
+			bp: 3,
+			unk1: 7,
+			flags: [Flag(10), Flag(11)],
+			name: TString("Lost Kitten".to_owned()),
+			client: TString("Someone".to_owned()),
+			desc: text("Find it"),
+			steps: vec![text("Look around"), text("Found it")],
+		}).into())),
+		(Game::Fc, Content::Town(vec![
+			Town { id: TownId(0), name: TString("Rolent".to_owned()), kind: 1 },
+			Town { id: TownId(1), name: TString("Bose".to_owned()), kind: 2 },
+		])),
+		(Game::Fc, Content::ED6World(vec![
+			ED6World { scena: FileId(0x00010000), pos: IVec2::new(100, -200) },
+		])),
+		(Game::Zero, Content::ED7Bgm(vec![
+			ED7Bgm { loop_start: 10, loop_end: 20, file_num: 3, id: BgmId(1), loops: true },
+		])),
+		(Game::Zero, Content::ED7Sound(vec![
+			ED7Sound { id: SoundId(5), file_num: 6, unk1: 7, unk2: [1, 2, 3, 4] },
+		])),
+		(Game::Fc, Content::ED6Ent(vec![ED6Ent {
+			name: TString("entrance".to_owned()),
+			bbox: (Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 2.0, 1.0)),
+			pos: Vec3::new(0.5, 0.0, 0.25),
+			angle: Angle(90),
+			unk1: 1,
+			flags: 0,
+			unk2: 2,
+			dest_name: "t0100".to_owned(),
+			dest: FileId(0x00010000),
+			dest_entrance: EntranceId(1),
+			unk3: 3,
+			cam_from: Vec3::new(0.0, 10.0, -10.0),
+			cam_deg: 45.0,
+			cam_zoom: 1.5,
+			cam_pers: 2000.0,
+			cam_at: Vec3::new(0.0, 1.0, 0.0),
+			cam_limit: (Angle(-30), Angle(30)),
+			town: TownId(1),
+			unk4: 4,
+		}])),
+	];
+
+	let write = |game, c: &Content| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+		Ok(match c {
+			Content::ED6Name(t1, t2) => ED6Name::write(game, t1, t2)?,
+			Content::ED7Name(t) => ED7Name::write(t)?,
+			Content::ED6Quest(t) => ED6Quest::write(t)?,
+			Content::ED7Quest(t) => ED7Quest::write(t)?,
+			Content::Town(t) => Town::write(game, t)?,
+			Content::ED6World(t) => ED6World::write(t)?,
+			Content::ED7Bgm(t) => ED7Bgm::write(t)?,
+			Content::ED7Sound(t) => ED7Sound::write(t)?,
+			Content::ED6Ent(t) => ED6Ent::write(t)?,
+			_ => unreachable!(),
+		})
+	};
+	let read = |game, c: &Content, data: &[u8]| -> Result<Content, Box<dyn std::error::Error>> {
+		Ok(match c {
+			Content::ED6Name(..) => {
+				let (t1, t2) = ED6Name::read(game, data)?;
+				Content::ED6Name(t1, t2)
+			}
+			Content::ED7Name(_) => Content::ED7Name(ED7Name::read(data)?),
+			Content::ED6Quest(_) => Content::ED6Quest(ED6Quest::read(data)?),
+			Content::ED7Quest(_) => Content::ED7Quest(ED7Quest::read(data)?),
+			Content::Town(_) => Content::Town(Town::read(game, data)?),
+			Content::ED6World(_) => Content::ED6World(ED6World::read(data)?),
+			Content::ED7Bgm(_) => Content::ED7Bgm(ED7Bgm::read(data)?),
+			Content::ED7Sound(_) => Content::ED7Sound(ED7Sound::read(data)?),
+			Content::ED6Ent(_) => Content::ED6Ent(ED6Ent::read(data)?),
+			_ => unreachable!(),
+		})
+	};
+
+	for (game, c) in cases {
+		let data = write(game, &c)?;
+		let src = crate::to_string(game, &read(game, &c, &data)?, None);
+		let (v, diags) = crate::parse(&src, None);
+		assert!(diags.is_empty(), "{src}\n{diags:?}");
+		let Some((game2, c2)) = v else { panic!("{src}") };
+		assert_eq!(game2, game);
+		assert!(write(game, &c2)? == data, "{src}");
+	}
+	Ok(())
+}
@@ -14,6 +14,7 @@ pub struct Context<'a> {
 	indent: usize,
 	space: Space,
 	pub lookup: &'a dyn Lookup,
+	func_names: Option<&'a crate::FuncNames>,
 	out: String,
 }
 
@@ -25,6 +26,7 @@ impl<'a> Context<'a> {
 			indent: 0,
 			space: Space::None,
 			lookup: lookup.unwrap_or_else(|| themelios::lookup::default_for(game)),
+			func_names: None,
 			out: String::new(),
 		}
 	}
@@ -34,6 +36,15 @@ impl<'a> Context<'a> {
 		self
 	}
 
+	pub fn func_names(mut self, names: &'a crate::FuncNames) -> Self {
+		self.func_names = Some(names);
+		self
+	}
+
+	pub fn func_name(&self, n: u16) -> Option<&'a str> {
+		self.func_names?.get(&n).map(|a| a.as_str())
+	}
+
 	pub fn finish(self) -> String {
 		self.out
 	}
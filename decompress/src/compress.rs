@@ -55,7 +55,7 @@ mod test {
 
 	fn check_compress(name: &'static str, d: &[u8], c: &[u8]) {
 		let mut d2 = Vec::with_capacity(d.len());
-		crate::decompress(c, &mut d2).unwrap();
+		crate::decompress_chunk(c, &mut d2).unwrap();
 		assert!(d2 == d, "\n[[[\n\n{}\n\n===\n\n{}\n\n]]]", String::from_utf8_lossy(d), String::from_utf8_lossy(&d2));
 		println!("{name}: {}", c.len() as f32 / d.len() as f32);
 	}
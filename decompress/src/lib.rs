@@ -26,10 +26,10 @@ pub fn decompress_ed6(f: &mut Reader) -> Result<Vec<u8>, Error> {
 
 pub fn compress_ed6(f: &mut Writer, data: &[u8]) {
 	for chunk in data.chunks(0xFFF0) {
-		let data = compress_chunk(chunk);
-		f.u16(data.len() as u16 + 2);
-		f.slice(&data);
-		f.u8((chunk.as_ptr_range().end == data.as_ptr_range().end).into());
+		let cdata = compress_chunk(chunk);
+		f.u16(cdata.len() as u16 + 2);
+		f.slice(&cdata);
+		f.u8((chunk.as_ptr_range().end != data.as_ptr_range().end).into());
 	}
 }
 
@@ -96,3 +96,11 @@ pub fn compress_ed7_to_vec(data: &[u8]) -> Vec<u8> {
 	compress_ed7(&mut w, data);
 	w.finish().unwrap()
 }
+
+#[test]
+fn test_ed6_chunks() {
+	// Big enough for three chunks, so that the continuation flags matter
+	let data = (0..0x24000u32).map(|i| (i ^ i >> 7).wrapping_mul(31) as u8).collect::<Vec<_>>();
+	let compressed = compress_ed6_to_vec(&data);
+	assert_eq!(decompress_ed6_from_slice(&compressed).unwrap(), data);
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::ValueHint;
+use themelios_archive::dirdat::{self, DirEntry};
+
+use crate::extract::raw_data;
+use crate::manifest::MANIFEST;
+use crate::pack::read_file;
+use crate::util::{mmap, get_archive_number};
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Build {
+	/// Directory containing the game's original .dir/.dat files.
+	#[clap(long, short, value_hint = ValueHint::DirPath)]
+	game: PathBuf,
+
+	/// Directory to write the patched .dir/.dat files to.
+	///
+	/// Only archives that are changed by a mod are written.
+	#[clap(long, short, value_hint = ValueHint::DirPath)]
+	output: PathBuf,
+
+	/// Store files as they are, rather than compressing them.
+	///
+	/// By default, files with an extension starting with an underscore, such as `._sn`, are compressed.
+	#[clap(long)]
+	no_compress: bool,
+
+	/// If several mods contain the same file, use the one listed last rather than failing.
+	#[clap(long)]
+	allow_conflicts: bool,
+
+	/// Mod directories, each containing subdirectories named after the archives, such as `ED6_DT01`,
+	/// which in turn contain the files to add or replace. This is the same layout as `factoria extract`
+	/// produces.
+	#[clap(required = true, value_hint = ValueHint::DirPath)]
+	mods: Vec<PathBuf>,
+}
+
+/// Rebuilds the game's archives with files from one or more mods.
+pub fn build(cmd: &Build) -> eyre::Result<()> {
+	// archive → name → paths of the file in each mod, in order
+	let mut files = BTreeMap::<u8, BTreeMap<String, Vec<PathBuf>>>::new();
+	for mod_dir in &cmd.mods {
+		for dir in std::fs::read_dir(mod_dir)? {
+			let dir = dir?.path();
+			if !dir.is_dir() {
+				continue
+			}
+			let Some(arch) = get_archive_number(&dir.with_extension("dir")) else {
+				eprintln!("{}: not named after an archive, ignoring", dir.display());
+				continue
+			};
+			for file in std::fs::read_dir(&dir)? {
+				let file = file?;
+				if !file.file_type()?.is_file() || file.file_name() == MANIFEST {
+					continue
+				}
+				let Some(name) = file.file_name().to_str().map(dirdat::normalize_name) else {
+					eyre::bail!("{}: invalid filename", file.path().display());
+				};
+				eyre::ensure!(dirdat::denormalize_name(&name).len() <= 12, "{}: name does not fit in 8.3 format", file.path().display());
+				files.entry(arch).or_default().entry(name).or_default().push(file.path());
+			}
+		}
+	}
+
+	let mut conflicts = 0;
+	for paths in files.values().flat_map(|a| a.values()) {
+		if paths.len() > 1 {
+			eprintln!("conflict: {}", paths.iter().map(|a| a.display().to_string()).collect::<Vec<_>>().join(", "));
+			conflicts += 1;
+		}
+	}
+	eyre::ensure!(conflicts == 0 || cmd.allow_conflicts, "found {conflicts} conflicts");
+
+	std::fs::create_dir_all(&cmd.output)?;
+	for (arch, files) in files {
+		let dir_file = cmd.game.join(format!("ED6_DT{arch:02X}.dir"));
+		let mut entries = dirdat::read_dir(&mmap(&dir_file)?)?;
+		let count = entries.capacity();
+		let dat = mmap(&dir_file.with_extension("dat"))?;
+		let mut data = Vec::with_capacity(entries.len());
+		for e in &entries {
+			data.push(raw_data(&dat, e)?.unwrap_or_default().to_owned());
+		}
+
+		for (name, paths) in files {
+			let path = paths.last().unwrap();
+			let (timestamp, mut file_data) = read_file(path)?;
+			if !cmd.no_compress && dirdat::is_compressed(&name) {
+				file_data = decompress::compress_ed6_to_vec(&file_data);
+			}
+
+			let (verb, index) = match entries.iter().position(|e| e.name == name) {
+				Some(index) => ("replace", index),
+				None => {
+					entries.push(DirEntry {
+						name: name.clone(),
+						unk1: 0,
+						compressed_size: 0,
+						unk3: 0,
+						archived_size: 0,
+						timestamp: 0,
+						offset: 0,
+					});
+					data.push(Vec::new());
+					("add", entries.len() - 1)
+				}
+			};
+
+			let e = &mut entries[index];
+			e.compressed_size = file_data.len();
+			e.unk3 = file_data.len();
+			e.archived_size = file_data.len();
+			e.timestamp = timestamp;
+			data[index] = file_data;
+			println!("{verb:7} 0x{arch:04X}{index:04X} {name} ({})", path.display());
+		}
+
+		let count = count.max(entries.len());
+		dirdat::layout(&mut entries, count);
+		let out = cmd.output.join(format!("ED6_DT{arch:02X}.dir"));
+		std::fs::write(out.with_extension("dat"), dirdat::write_dat(&entries, count, &data)?)?;
+		std::fs::write(&out, dirdat::write_dir(&entries, count)?)?;
+	}
+	Ok(())
+}
@@ -0,0 +1,43 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::ValueHint;
+use themelios_archive::Archives;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Cat {
+	/// Print files exactly as they are stored in the archive, without decompressing them.
+	#[clap(long)]
+	no_decompress: bool,
+
+	/// The directory containing the .dir/.dat files.
+	#[clap(value_hint = ValueHint::DirPath)]
+	dir: PathBuf,
+
+	/// Names or file ids of the files to print, such as `t0100._sn` or `0x00010023`.
+	#[clap(required = true)]
+	files: Vec<String>,
+}
+
+pub fn cat(cmd: &Cat) -> eyre::Result<()> {
+	let archives = Archives::new(&cmd.dir)?;
+	let mut out = std::io::stdout().lock();
+	for file in &cmd.files {
+		let id = parse_file(&archives, file)?;
+		if cmd.no_decompress {
+			out.write_all(archives.get_raw(id)?)?;
+		} else {
+			out.write_all(&archives.get(id)?)?;
+		}
+	}
+	Ok(())
+}
+
+/// Parses a file id in hex, or looks up a filename.
+pub fn parse_file(archives: &Archives, file: &str) -> eyre::Result<u32> {
+	if let Some(hex) = file.strip_prefix("0x").or_else(|| file.strip_prefix("0X")) {
+		Ok(u32::from_str_radix(hex, 16)?)
+	} else {
+		Ok(archives.index(&file.to_lowercase())?)
+	}
+}
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::ValueHint;
+use themelios_archive::Archives;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Diff {
+	/// Instead of describing the changes, print one line per changed archive, consisting of the new
+	/// .dir file followed by the names of the added or changed files.
+	///
+	/// Each line can be passed as arguments to `factoria extract`, for example with `xargs -L1`.
+	#[clap(long)]
+	extract_list: bool,
+
+	/// Directory containing the old `ED6_DTxx.dir/.dat` files.
+	#[clap(value_hint = ValueHint::DirPath)]
+	old: PathBuf,
+
+	/// Directory containing the new `ED6_DTxx.dir/.dat` files.
+	#[clap(value_hint = ValueHint::DirPath)]
+	new: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+	Added,
+	Removed,
+	Resized(usize, usize),
+	Changed,
+}
+
+pub fn diff(cmd: &Diff) -> eyre::Result<()> {
+	let old = Archives::new(&cmd.old)?;
+	let new = Archives::new(&cmd.new)?;
+
+	for arch in 0..64 {
+		let old_files = files(&old, arch);
+		let new_files = files(&new, arch);
+
+		let mut changes = Vec::new();
+		for (name, &a) in &old_files {
+			let Some(&b) = new_files.get(name) else {
+				changes.push((name, Change::Removed));
+				continue
+			};
+			let a = get(&old, a)?;
+			let b = get(&new, b)?;
+			if a.len() != b.len() {
+				changes.push((name, Change::Resized(a.len(), b.len())));
+			} else if a != b {
+				changes.push((name, Change::Changed));
+			}
+		}
+		for name in new_files.keys() {
+			if !old_files.contains_key(name) {
+				changes.push((name, Change::Added));
+			}
+		}
+		changes.sort_by_key(|(name, _)| *name);
+
+		if cmd.extract_list {
+			let names = changes.iter()
+				.filter(|(_, c)| *c != Change::Removed)
+				.map(|(name, _)| name.as_str())
+				.collect::<Vec<_>>();
+			if !names.is_empty() {
+				let dir_file = cmd.new.join(format!("ED6_DT{arch:02X}.dir"));
+				println!("{} {}", dir_file.display(), names.join(" "));
+			}
+		} else {
+			for (name, change) in changes {
+				match change {
+					Change::Added => println!("added    ED6_DT{arch:02X}/{name}"),
+					Change::Removed => println!("removed  ED6_DT{arch:02X}/{name}"),
+					Change::Resized(a, b) => println!("resized  ED6_DT{arch:02X}/{name} ({a} → {b} bytes)"),
+					Change::Changed => println!("changed  ED6_DT{arch:02X}/{name}"),
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+/// The non-deleted files in an archive, by name.
+fn files(archives: &Archives, arch: u8) -> BTreeMap<&String, u32> {
+	let entries = archives.entries(arch).unwrap_or_default();
+	entries.iter().enumerate()
+		.filter(|(_, e)| e.range().is_some())
+		.map(|(i, e)| (&e.name, (arch as u32) << 16 | i as u32))
+		.collect()
+}
+
+/// Gets the decompressed data of a file, or the raw data if it fails to decompress.
+fn get(archives: &Archives, id: u32) -> eyre::Result<Cow<'_, [u8]>> {
+	match archives.get(id) {
+		Ok(data) => Ok(data),
+		Err(e) if e.kind() == std::io::ErrorKind::InvalidData => Ok(Cow::Borrowed(archives.get_raw(id)?)),
+		Err(e) => Err(e.into()),
+	}
+}
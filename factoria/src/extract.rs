@@ -0,0 +1,109 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use clap::ValueHint;
+use themelios_archive::dirdat::{self, DirEntry};
+
+use crate::manifest::{self, Manifest};
+use crate::util::mmap;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Extract {
+	/// Directory to place resulting files in.
+	///
+	/// If unspecified, a directory will be created adjacent to the dir file, with the .dir suffix removed.
+	#[clap(long, short, value_hint = ValueHint::DirPath)]
+	output: Option<PathBuf>,
+
+	/// Write files exactly as they are stored in the archive, without decompressing them.
+	#[clap(long)]
+	no_decompress: bool,
+
+	/// The .dir file to extract.
+	///
+	/// Besides the files themselves, a `manifest.json` is written containing the metadata of the
+	/// archive, and the files' modification times are set to the timestamps in the archive. This
+	/// allows `factoria pack` to rebuild the archive exactly, as long as the original archive is still
+	/// there to copy unchanged compressed files and files that were not extracted from.
+	#[clap(value_hint = ValueHint::FilePath)]
+	dir_file: PathBuf,
+
+	/// Globs of filenames to extract.
+	///
+	/// If unspecified, extracts all files.
+	names: Vec<String>,
+}
+
+pub fn extract(cmd: &Extract) -> eyre::Result<()> {
+	let globs = cmd.names.iter()
+		.map(|a| glob::Pattern::new(a))
+		.collect::<Result<Vec<_>, _>>()?;
+	let glob_opts = glob::MatchOptions {
+		case_sensitive: false,
+		..Default::default()
+	};
+
+	let entries = dirdat::read_dir(&mmap(&cmd.dir_file)?)?;
+	let dat = mmap(&cmd.dir_file.with_extension("dat"))?;
+
+	let outdir = cmd.output.clone().unwrap_or_else(|| cmd.dir_file.with_extension(""));
+	std::fs::create_dir_all(&outdir)?;
+
+	let mut manifest = Manifest {
+		count: entries.capacity(),
+		archive: std::fs::canonicalize(&cmd.dir_file).ok(),
+		entries: Vec::with_capacity(entries.len()),
+	};
+
+	for e in &entries {
+		let decompress = !cmd.no_decompress && dirdat::is_compressed(&e.name);
+		if !globs.is_empty() && !globs.iter().any(|g| g.matches_with(&e.name, glob_opts)) {
+			manifest.entries.push(manifest::Entry {
+				hash: raw_data(&dat, e)?.map(manifest::hash),
+				skipped: true,
+				..manifest::Entry::new(e, false)
+			});
+			continue
+		}
+
+		let Some(data) = raw_data(&dat, e)? else {
+			manifest.entries.push(manifest::Entry::new(e, false));
+			continue
+		};
+
+		let (data, decompressed) = if decompress {
+			match decompress::decompress_ed6_from_slice(data) {
+				Ok(data) => (data, true),
+				Err(err) => {
+					eprintln!("{}: failed to decompress, writing raw data: {err}", e.name);
+					(data.to_owned(), false)
+				}
+			}
+		} else {
+			(data.to_owned(), false)
+		};
+		manifest.entries.push(manifest::Entry {
+			hash: decompressed.then(|| manifest::hash(&data)),
+			..manifest::Entry::new(e, decompressed)
+		});
+
+		let file = std::fs::File::create(outdir.join(&e.name))?;
+		(&file).write_all(&data)?;
+		file.set_modified(UNIX_EPOCH + Duration::from_secs(e.timestamp.into()))?;
+	}
+
+	manifest.write(&outdir)?;
+	Ok(())
+}
+
+/// Returns the stored data of an entry, without the padding some archives have.
+///
+/// Deleted entries have no data and return `None`.
+pub fn raw_data<'a>(dat: &'a [u8], e: &DirEntry) -> eyre::Result<Option<&'a [u8]>> {
+	let Some(range) = e.range() else { return Ok(None) };
+	let Some(data) = dat.get(range.clone()) else {
+		eyre::bail!("{}: range {:#X}..{:#X} is out of bounds of the .dat file (size {:#X})", e.name, range.start, range.end, dat.len());
+	};
+	Ok(Some(&data[..e.compressed_size.min(data.len())]))
+}
@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueHint;
+use themelios::identify::identify as identify_data;
+use themelios::types::Game;
+use themelios_archive::dirdat;
+
+use crate::extract::raw_data;
+use crate::util::{mmap, get_archive_number};
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Identify {
+	/// Which game the files are from.
+	#[clap(long, short, value_enum)]
+	game: Game,
+
+	/// Files to identify.
+	///
+	/// .dir files identify every file in the archive, and directories every file in the directory.
+	#[clap(required = true, value_hint = ValueHint::AnyPath)]
+	files: Vec<PathBuf>,
+}
+
+pub fn identify(cmd: &Identify) -> eyre::Result<()> {
+	let game = cmd.game;
+	for path in &cmd.files {
+		if path.is_dir() {
+			let mut files = std::fs::read_dir(path)?
+				.map(|a| a.map(|a| a.path()))
+				.collect::<Result<Vec<_>, _>>()?;
+			files.sort();
+			for file in files.into_iter().filter(|a| a.is_file()) {
+				identify_file(game, &file)?;
+			}
+		} else if path.extension().is_some_and(|a| a == "dir") {
+			identify_archive(game, path)?;
+		} else {
+			identify_file(game, path)?;
+		}
+	}
+	Ok(())
+}
+
+fn identify_file(game: Game, path: &Path) -> eyre::Result<()> {
+	let name = path.file_name().and_then(|a| a.to_str()).unwrap_or_default();
+	let data = std::fs::read(path)?;
+	println!("{} {}", kind(game, name, &data), path.display());
+	Ok(())
+}
+
+fn identify_archive(game: Game, dir_file: &Path) -> eyre::Result<()> {
+	let archive_number = get_archive_number(dir_file);
+	let entries = dirdat::read_dir(&mmap(dir_file)?)?;
+	let dat = mmap(&dir_file.with_extension("dat"))?;
+	for (index, e) in entries.iter().enumerate() {
+		let Some(data) = raw_data(&dat, e)? else { continue };
+		let kind = if dirdat::is_compressed(&e.name) {
+			match decompress::decompress_ed6_from_slice(data) {
+				Ok(data) => kind(game, &e.name, &data),
+				Err(_) => "corrupt".to_owned(),
+			}
+		} else {
+			kind(game, &e.name, data)
+		};
+
+		if let Some(arch) = archive_number {
+			println!("0x{:04X}{:04X} {kind} {}", arch, index, e.name);
+		} else {
+			println!("0x{:04X} {kind} {}", index, e.name);
+		}
+	}
+	Ok(())
+}
+
+fn kind(game: Game, name: &str, data: &[u8]) -> String {
+	match identify_data(game, name, data) {
+		Some(kind) => kind.to_string(),
+		None => "unknown".to_owned(),
+	}
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use clap::ValueHint;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct MakeIndex {
+	/// The directory to generate the indexes from.
+	/// Should be either the root directory for the PC games (containing the .dir/.dat files),
+	/// or the data/data_sc/data_3rd directory for Evolution.
+	///
+	/// Can also be an existing index file, to convert it to another format.
+	#[clap(value_hint = ValueHint::AnyPath)]
+	dir: PathBuf,
+
+	/// Other indexes to add on top, such as a text file listing the files added by a mod.
+	#[clap(long, value_hint = ValueHint::FilePath)]
+	add: Vec<PathBuf>,
+
+	/// Where to write the index.
+	///
+	/// If the name ends with .zst, the index is compressed the same way as Calmare's built-in indexes.
+	/// If it ends with .txt, it is written in a plain text format suitable for editing by hand.
+	#[clap(long, short, value_hint = ValueHint::FilePath)]
+	output: PathBuf,
+}
+
+pub fn make_index(cmd: &MakeIndex) -> eyre::Result<()> {
+	let lookup = themelios::lookup::load_layered(&cmd.dir, &cmd.add)?.to_ed6lookup();
+
+	let data = if cmd.output.extension().is_some_and(|a| a == "zst") {
+		themelios::lookup::write_ed6i_zst(&lookup)?
+	} else if cmd.output.extension().is_some_and(|a| a == "txt") {
+		lookup.write_txt().into_bytes()
+	} else {
+		lookup.write_ed6i()?
+	};
+	std::fs::write(&cmd.output, data)?;
+	Ok(())
+}
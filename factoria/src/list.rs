@@ -1,10 +1,11 @@
 use std::{path::{PathBuf, Path}, borrow::Cow};
+use std::io::Write;
 
 use clap::ValueHint;
 use themelios_archive::dirdat::{self, DirEntry};
 use unicode_width::UnicodeWidthStr;
 
-use crate::util::mmap;
+use crate::util::{mmap, get_archive_number};
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct List {
@@ -15,11 +16,25 @@ pub struct List {
 	#[clap(short='1')]
 	one_per_line: bool,
 
+	/// Output format.
+	///
+	/// The machine-readable formats include all fields of each entry, and ignore the other display options.
+	#[clap(long, value_enum, default_value_t = Format::Table)]
+	format: Format,
+
 	/// The .dir file to inspect.
 	#[clap(value_hint = ValueHint::FilePath, required = true)]
 	dir_file: Vec<PathBuf>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+	Table,
+	Json,
+	Csv,
+	Tsv,
+}
+
 struct Cell<'a> {
 	width: usize,
 	right: bool,
@@ -100,6 +115,10 @@ impl std::fmt::Display for Table<'_> {
 }
 
 pub fn list(cmd: &List) -> eyre::Result<()> {
+	if cmd.format != Format::Table {
+		return list_data(cmd)
+	}
+
 	for (idx, dir_file) in cmd.dir_file.iter().enumerate() {
 		if cmd.dir_file.len() != 1 {
 			println!("{}:", dir_file.display());
@@ -169,6 +188,79 @@ fn list_one(cmd: &List, dir_file: &Path) -> eyre::Result<()> {
 	Ok(())
 }
 
+#[derive(serde::Serialize)]
+struct Record<'a> {
+	archive: Option<u8>,
+	index: usize,
+	id: u32,
+	name: &'a str,
+	unk1: u32,
+	compressed_size: usize,
+	unk3: usize,
+	archived_size: usize,
+	timestamp: u32,
+	offset: usize,
+}
+
+fn list_data(cmd: &List) -> eyre::Result<()> {
+	let mut archives = Vec::new();
+	for dir_file in &cmd.dir_file {
+		archives.push((get_archive_number(dir_file), dirdat::read_dir(&mmap(dir_file)?)?));
+	}
+
+	let records = archives.iter().flat_map(|(arch, entries)| {
+		entries.iter().enumerate().map(move |(index, e)| Record {
+			archive: *arch,
+			index,
+			id: arch.map_or(0, |a| (a as u32) << 16) | index as u32,
+			name: &e.name,
+			unk1: e.unk1,
+			compressed_size: e.compressed_size,
+			unk3: e.unk3,
+			archived_size: e.archived_size,
+			timestamp: e.timestamp,
+			offset: e.offset,
+		})
+	});
+
+	let mut out = std::io::stdout().lock();
+	let sep = match cmd.format {
+		Format::Table => unreachable!(),
+		Format::Json => {
+			serde_json::to_writer_pretty(&mut out, &records.collect::<Vec<_>>())?;
+			writeln!(out)?;
+			return Ok(())
+		}
+		Format::Csv => ",",
+		Format::Tsv => "\t",
+	};
+
+	writeln!(out, "{}", [
+		"archive", "index", "id", "name", "unk1", "compressed_size",
+		"unk3", "archived_size", "timestamp", "offset",
+	].join(sep))?;
+	for r in records {
+		let name = if sep == "," && r.name.contains([',', '"', '\n']) {
+			format!("\"{}\"", r.name.replace('"', "\"\""))
+		} else {
+			r.name.to_owned()
+		};
+		writeln!(out, "{}", [
+			r.archive.map_or(String::new(), |a| a.to_string()),
+			r.index.to_string(),
+			r.id.to_string(),
+			name,
+			r.unk1.to_string(),
+			r.compressed_size.to_string(),
+			r.unk3.to_string(),
+			r.archived_size.to_string(),
+			r.timestamp.to_string(),
+			r.offset.to_string(),
+		].join(sep))?;
+	}
+	Ok(())
+}
+
 fn size(cmd: &List, mut size: usize) -> String {
 	if cmd.human_readable {
 		if size < 768 {
@@ -185,15 +277,6 @@ fn size(cmd: &List, mut size: usize) -> String {
 	size.to_string()
 }
 
-fn get_archive_number(path: &Path) -> Option<u8> {
-	let name = path
-		.file_name()?
-		.to_str()?
-		.strip_prefix("ED6_DT")?
-		.strip_suffix(".dir")?;
-	u8::from_str_radix(name, 16).ok()
-}
-
 fn format_name(e: &DirEntry) -> Cell {
 	if e.timestamp == 0 {
 		Cell::new(&e.name).format("2")
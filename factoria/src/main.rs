@@ -1,10 +1,18 @@
 #![feature(let_chains)]
 
-use std::path::PathBuf;
-
-use clap::{Parser, ValueHint};
+use clap::Parser;
 mod util;
 mod list;
+mod extract;
+mod patch;
+mod index;
+mod cat;
+mod verify;
+mod diff;
+mod manifest;
+mod pack;
+mod identify;
+mod build;
 
 #[derive(Debug, Clone, Parser)]
 struct Cli {
@@ -12,54 +20,50 @@ struct Cli {
 	command: Command,
 }
 
-#[derive(Debug, Clone, clap::Args)]
-struct Extract {
-	/// Directory to place resulting files in.
-	///
-	/// If unspecified, a directory will be created adjacent to the dir file, with the .dir suffix removed.
-	#[clap(long, short, value_hint = ValueHint::DirPath)]
-	output: Option<PathBuf>,
-	#[clap(long, default_value_t = true)]
-	no_decompress: bool,
-
-	/// The .dir file to extract.
-	#[clap(value_hint = ValueHint::FilePath)]
-	dir_file: PathBuf,
-
-	/// Globs of filenames to extract.
-	///
-	/// If unspecified, extracts all files.
-	names: Vec<String>,
-}
-
-#[derive(Debug, Clone, clap::Args)]
-struct MakeIndex {
-	/// The directory to generate the indexes from.
-	/// Should be either the root directory for the PC games (containing the .dir/.dat files),
-	/// or the data/data_sc/data_3rd directory for Evolution.
-	#[clap(value_hint = ValueHint::DirPath)]
-	dir: PathBuf,
-	#[clap(long, short, value_hint = ValueHint::DirPath)]
-	output: PathBuf,
-}
-
 #[derive(Debug, Clone, clap::Subcommand)]
 enum Command {
 	/// List archive contents.
 	#[clap(visible_alias = "ls")]
 	List(list::List),
+	/// Extract files from an archive.
 	#[clap(visible_alias = "x")]
-	Extract(Extract),
+	Extract(extract::Extract),
+	/// Print files from a game's archives to stdout.
+	#[clap(visible_alias = "get")]
+	Cat(cat::Cat),
+	/// Create an archive from a directory of files.
+	Pack(pack::Pack),
+	/// Apply mods to a game's archives, writing the patched archives to a new directory.
+	Build(build::Build),
+	/// Add new files to an archive, in place.
+	Add(patch::Patch),
+	/// Replace existing files in an archive, in place.
+	Replace(patch::Patch),
+	/// Generate an .ed6i index of the file ids in a game's archives.
 	#[clap(visible_alias = "index")]
-	MakeIndex(MakeIndex),
+	MakeIndex(index::MakeIndex),
+	/// Check archives for inconsistencies and corrupted data.
+	Verify(verify::Verify),
+	/// Compare the archives of two game installations.
+	Diff(diff::Diff),
+	/// Guess what kind of data files contain.
+	Identify(identify::Identify),
 }
 
 fn main() -> eyre::Result<()> {
 	let cli = Cli::parse();
 	match cli.command {
 		Command::List(cmd) => list::list(&cmd)?,
-		Command::Extract(cmd) => todo!(),
-		Command::MakeIndex(cmd) => todo!(),
+		Command::Extract(cmd) => extract::extract(&cmd)?,
+		Command::Cat(cmd) => cat::cat(&cmd)?,
+		Command::Pack(cmd) => pack::pack(&cmd)?,
+		Command::Build(cmd) => build::build(&cmd)?,
+		Command::Add(cmd) => patch::add(&cmd)?,
+		Command::Replace(cmd) => patch::replace(&cmd)?,
+		Command::MakeIndex(cmd) => index::make_index(&cmd)?,
+		Command::Verify(cmd) => verify::verify(&cmd)?,
+		Command::Diff(cmd) => diff::diff(&cmd)?,
+		Command::Identify(cmd) => identify::identify(&cmd)?,
 	}
 	Ok(())
 }
@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use themelios_archive::dirdat::DirEntry;
+
+/// Name of the manifest file written by `extract` and read by `pack`.
+///
+/// Since archive entries are limited to 8.3 names, this can never collide with an extracted file.
+pub const MANIFEST: &str = "manifest.json";
+
+/// The information needed to rebuild an archive exactly as it was, except for the file data itself.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+	/// Total number of slots in the archive, including the `/_______.___` ones.
+	pub count: usize,
+	/// The .dir file that was extracted. Files that were not extracted, or were decompressed but not
+	/// changed, are copied from here, so that they are not compressed differently.
+	#[serde(default)]
+	pub archive: Option<PathBuf>,
+	pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+	pub name: String,
+	pub unk1: u32,
+	pub compressed_size: usize,
+	pub unk3: usize,
+	pub archived_size: usize,
+	pub timestamp: u32,
+	pub offset: usize,
+	/// Whether the file was decompressed when extracted, and thus needs to be compressed when packed.
+	pub decompressed: bool,
+	/// For decompressed files, a [`hash`] of the decompressed data, to tell whether it has changed.
+	/// For skipped files, a hash of the raw data, to tell whether the archive has changed.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub hash: Option<u64>,
+	/// Whether the file was left out when extracting, and needs to be copied from the archive.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub skipped: bool,
+}
+
+impl Entry {
+	pub fn new(e: &DirEntry, decompressed: bool) -> Self {
+		Entry {
+			name: e.name.clone(),
+			unk1: e.unk1,
+			compressed_size: e.compressed_size,
+			unk3: e.unk3,
+			archived_size: e.archived_size,
+			timestamp: e.timestamp,
+			offset: e.offset,
+			decompressed,
+			hash: None,
+			skipped: false,
+		}
+	}
+
+	pub fn to_dir_entry(&self) -> DirEntry {
+		DirEntry {
+			name: self.name.clone(),
+			unk1: self.unk1,
+			compressed_size: self.compressed_size,
+			unk3: self.unk3,
+			archived_size: self.archived_size,
+			timestamp: self.timestamp,
+			offset: self.offset,
+		}
+	}
+}
+
+impl Manifest {
+	/// Reads the manifest in a directory, if there is one.
+	pub fn read(dir: &Path) -> eyre::Result<Option<Manifest>> {
+		match std::fs::read(dir.join(MANIFEST)) {
+			Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	pub fn write(&self, dir: &Path) -> eyre::Result<()> {
+		let mut data = serde_json::to_vec_pretty(self)?;
+		data.push(b'\n');
+		std::fs::write(dir.join(MANIFEST), data)?;
+		Ok(())
+	}
+}
+
+/// A hash of a file's contents, to tell whether it has changed since it was extracted.
+pub fn hash(data: &[u8]) -> u64 {
+	// FNV-1a, since unlike std's hasher it is guaranteed to stay the same
+	data.iter().fold(0xCBF29CE484222325, |h, &b| (h ^ b as u64).wrapping_mul(0x100000001B3))
+}
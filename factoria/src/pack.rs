@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use clap::ValueHint;
+use themelios_archive::dirdat::{self, DirEntry};
+
+use crate::extract::raw_data;
+use crate::manifest::{self, Manifest, MANIFEST};
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Pack {
+	/// The .dir file to write. The corresponding .dat file is written too.
+	///
+	/// If unspecified, it is placed adjacent to the input directory, with a .dir suffix.
+	#[clap(long, short, value_hint = ValueHint::FilePath)]
+	output: Option<PathBuf>,
+
+	/// Store new files as they are, rather than compressing them.
+	///
+	/// Files listed in the manifest are compressed only if they were decompressed when extracted.
+	#[clap(long)]
+	no_compress: bool,
+
+	/// Directory containing the files to pack, such as one created by `factoria extract`.
+	#[clap(value_hint = ValueHint::DirPath)]
+	dir: PathBuf,
+}
+
+/// Builds an archive from a directory.
+///
+/// If the directory has a manifest, the entries are written in the same slots, with the same metadata,
+/// and as long as all files have the same size as before, at the same offsets. Timestamps are taken
+/// from the files' modification times, which `extract` sets to the original timestamps.
+///
+/// The compressor does not produce the same output as the one used by Falcom, so decompressed files
+/// that have not changed are copied from the original archive instead, as are files that were not
+/// extracted at all. If the original archive is gone or has changed, unchanged files are compressed
+/// anew, which moves everything after them.
+///
+/// Files not in the manifest are added at the end.
+pub fn pack(cmd: &Pack) -> eyre::Result<()> {
+	let mut files = BTreeMap::new();
+	for file in std::fs::read_dir(&cmd.dir)? {
+		let file = file?;
+		if !file.file_type()?.is_file() || file.file_name() == MANIFEST {
+			continue
+		}
+		let Some(name) = file.file_name().to_str().map(dirdat::normalize_name) else {
+			eyre::bail!("{}: invalid filename", file.path().display());
+		};
+		eyre::ensure!(dirdat::denormalize_name(&name).len() <= 12, "{name}: name does not fit in 8.3 format");
+		files.insert(name, file.path());
+	}
+
+	let manifest = Manifest::read(&cmd.dir)?;
+	let mut entries = Vec::new();
+	let mut data = Vec::new();
+	let mut relayout = manifest.is_none();
+
+	// Raw data is only copied from the original archive if its entry is still the same as in the
+	// manifest, since the archive may have been updated or patched since it was extracted
+	let mut archive = None;
+	let mut original = |index: usize, m: &manifest::Entry| -> eyre::Result<Option<Vec<u8>>> {
+		let Some(path) = manifest.as_ref().and_then(|m| m.archive.as_ref()) else { return Ok(None) };
+		let archive = archive.get_or_insert_with(|| {
+			let read = || -> eyre::Result<_> {
+				let entries = dirdat::read_dir(&std::fs::read(path)?)?;
+				let dat = std::fs::read(path.with_extension("dat"))?;
+				Ok((entries, dat))
+			};
+			read().inspect_err(|err| eprintln!("{}: could not read original archive: {err}", path.display())).ok()
+		});
+		let Some((entries, dat)) = archive else { return Ok(None) };
+		let e = m.to_dir_entry();
+		if entries.get(index) != Some(&e) {
+			return Ok(None)
+		}
+		let Some(raw) = raw_data(dat, &e)? else { return Ok(None) };
+		let hash = if m.decompressed {
+			decompress::decompress_ed6_from_slice(raw).ok().map(|a| manifest::hash(&a))
+		} else {
+			Some(manifest::hash(raw))
+		};
+		if m.hash.is_some() && hash != m.hash {
+			return Ok(None)
+		}
+		Ok(Some(raw.to_owned()))
+	};
+
+	for (index, m) in manifest.iter().flat_map(|m| &m.entries).enumerate() {
+		let mut e = m.to_dir_entry();
+		if e.range().is_none() {
+			entries.push(e);
+			data.push(Vec::new());
+			continue
+		}
+
+		let file_data = match files.remove(&m.name) {
+			Some(path) => {
+				let (timestamp, file_data) = read_file(&path)?;
+				e.timestamp = timestamp;
+				if m.decompressed {
+					let unchanged = m.hash == Some(manifest::hash(&file_data));
+					match unchanged.then(|| original(index, m)).transpose()?.flatten() {
+						Some(raw) => raw,
+						None => decompress::compress_ed6_to_vec(&file_data),
+					}
+				} else if m.skipped && !cmd.no_compress && dirdat::is_compressed(&m.name) {
+					decompress::compress_ed6_to_vec(&file_data)
+				} else {
+					file_data
+				}
+			}
+			None if m.skipped => match original(index, m)? {
+				Some(raw) => raw,
+				None => eyre::bail!("{}: was not extracted, and the original archive is not available or has changed", m.name),
+			},
+			None => eyre::bail!("{}: listed in manifest, but does not exist", m.name),
+		};
+
+		if file_data.len() != e.compressed_size {
+			e.compressed_size = file_data.len();
+			e.unk3 = file_data.len();
+			e.archived_size = file_data.len();
+			relayout = true;
+		}
+		entries.push(e);
+		data.push(file_data);
+	}
+
+	for (name, path) in files {
+		let (timestamp, mut file_data) = read_file(&path)?;
+		if !cmd.no_compress && dirdat::is_compressed(&name) {
+			file_data = decompress::compress_ed6_to_vec(&file_data);
+		}
+		entries.push(DirEntry {
+			name,
+			unk1: 0,
+			compressed_size: file_data.len(),
+			unk3: file_data.len(),
+			archived_size: file_data.len(),
+			timestamp,
+			offset: 0,
+		});
+		data.push(file_data);
+		relayout = true;
+	}
+
+	let count = manifest.map_or(0, |m| m.count).max(entries.len());
+	if relayout {
+		dirdat::layout(&mut entries, count);
+	}
+
+	let output = cmd.output.clone().unwrap_or_else(|| cmd.dir.with_extension("dir"));
+	std::fs::write(output.with_extension("dat"), dirdat::write_dat(&entries, count, &data)?)?;
+	std::fs::write(&output, dirdat::write_dir(&entries, count)?)?;
+	Ok(())
+}
+
+/// Reads a file, along with its modification time as a timestamp.
+pub fn read_file(path: &std::path::Path) -> eyre::Result<(u32, Vec<u8>)> {
+	let file = std::fs::File::open(path)?;
+	let timestamp = file.metadata()?.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+	let data = std::fs::read(path)?;
+	// Zero means deleted, so avoid that
+	Ok((u32::try_from(timestamp)?.max(1), data))
+}
+
+#[test]
+fn test_roundtrip() -> eyre::Result<()> {
+	use clap::Parser;
+	use std::ffi::OsStr;
+	let run = |args: &[&OsStr]| match crate::Cli::parse_from(std::iter::once(OsStr::new("factoria")).chain(args.iter().copied())).command {
+		crate::Command::Extract(cmd) => crate::extract::extract(&cmd),
+		crate::Command::Pack(cmd) => pack(&cmd),
+		_ => unreachable!(),
+	};
+
+	let dir = std::env::temp_dir().join(format!("factoria-pack-{}", std::process::id()));
+	std::fs::create_dir_all(&dir)?;
+	let dir_file = dir.join("ED6_DT99.dir");
+
+	// Compressed in two chunks, unlike what compress_ed6 would do
+	let text = b"scena scena scena scena, and some more scena".repeat(4);
+	let mut sn = Vec::new();
+	for (chunk, more) in [(&text[..20], 1), (&text[20..], 0)] {
+		let c = decompress::compress_chunk(chunk);
+		sn.extend((c.len() as u16 + 2).to_le_bytes());
+		sn.extend(c);
+		sn.push(more);
+	}
+	assert_ne!(sn, decompress::compress_ed6_to_vec(&text));
+
+	let mut entries = vec![
+		DirEntry { name: "t0100._sn".into(), unk1: 0, compressed_size: sn.len(), unk3: 1024, archived_size: sn.len() + 3, timestamp: 1234, offset: 0 },
+		DirEntry { name: "gone._ch".into(), unk1: 0, compressed_size: 0, unk3: 0, archived_size: 0, timestamp: 0, offset: 0 },
+		DirEntry { name: "a.txt".into(), unk1: 7, compressed_size: 5, unk3: 5, archived_size: 5, timestamp: 5678, offset: 0 },
+	];
+	dirdat::layout(&mut entries, 5);
+	std::fs::write(&dir_file, dirdat::write_dir(&entries, 5)?)?;
+	std::fs::write(dir_file.with_extension("dat"), dirdat::write_dat(&entries, 5, &[&sn[..], b"", b"hello"])?)?;
+
+	for (name, globs) in [("all", &[][..]), ("some", &["*.txt"][..])] {
+		let out = dir.join(name);
+		let packed = dir.join(format!("{name}.dir"));
+		let mut args = vec![OsStr::new("extract"), "-o".as_ref(), out.as_os_str(), dir_file.as_os_str()];
+		args.extend(globs.iter().map(OsStr::new));
+		run(&args)?;
+		run(&["pack".as_ref(), "-o".as_ref(), packed.as_os_str(), out.as_os_str()])?;
+		assert!(std::fs::read(&packed)? == std::fs::read(&dir_file)?, "{name}: .dir differs");
+		assert!(std::fs::read(packed.with_extension("dat"))? == std::fs::read(dir_file.with_extension("dat"))?, "{name}: .dat differs");
+	}
+
+	// If the original archive has changed since, its data is not reused
+	let mut dat = std::fs::read(dir_file.with_extension("dat"))?;
+	let e = &entries[0];
+	dat[e.offset..e.offset + e.compressed_size].fill(0);
+	std::fs::write(dir_file.with_extension("dat"), dat)?;
+	let packed = dir.join("changed.dir");
+	run(&["pack".as_ref(), "-o".as_ref(), packed.as_os_str(), dir.join("all").as_os_str()])?;
+	let e = &dirdat::read_dir(&std::fs::read(&packed)?)?[0];
+	let dat = std::fs::read(packed.with_extension("dat"))?;
+	assert_eq!(decompress::decompress_ed6_from_slice(&dat[e.offset..e.offset + e.compressed_size])?, text);
+	assert!(run(&["pack".as_ref(), "-o".as_ref(), packed.as_os_str(), dir.join("some").as_os_str()]).is_err());
+
+	std::fs::remove_dir_all(&dir)?;
+	Ok(())
+}
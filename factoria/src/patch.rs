@@ -0,0 +1,220 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use clap::ValueHint;
+use themelios_archive::dirdat::{self, DirEntry};
+
+use crate::util::get_archive_number;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Patch {
+	/// Store the files as they are, rather than compressing them.
+	///
+	/// By default, files with an extension starting with an underscore, such as `._sn`, are compressed.
+	#[clap(long)]
+	no_compress: bool,
+
+	/// The .dir file to modify. The corresponding .dat file is modified too.
+	#[clap(value_hint = ValueHint::FilePath)]
+	dir_file: PathBuf,
+
+	/// Files to insert. Archive entries are named after the filename.
+	#[clap(required = true, value_hint = ValueHint::FilePath)]
+	files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+	Add,
+	Replace,
+}
+
+pub fn add(cmd: &Patch) -> eyre::Result<()> {
+	patch(cmd, Mode::Add)
+}
+
+pub fn replace(cmd: &Patch) -> eyre::Result<()> {
+	patch(cmd, Mode::Replace)
+}
+
+/// Modifies the archive in place.
+///
+/// Where possible, the new data is written over the old: either it fits in the old space, or the
+/// entry is at the end of the .dat and can grow. Otherwise, it is appended to the end of the .dat,
+/// and only that entry's offset is changed. New entries are placed in the `/_______.___` slots, so
+/// if there are none left, the archive needs to be rebuilt in full.
+fn patch(cmd: &Patch, mode: Mode) -> eyre::Result<()> {
+	let archive_number = get_archive_number(&cmd.dir_file);
+	let mut entries = dirdat::read_dir(&std::fs::read(&cmd.dir_file)?)?;
+	let count = entries.capacity();
+
+	let mut dat = OpenOptions::new().read(true).write(true).open(cmd.dir_file.with_extension("dat"))?;
+	let mut head = [0; 16];
+	dat.read_exact(&mut head)?;
+	eyre::ensure!(head[..8] == *b"LB DAT\x1A\0", "not a .dat file");
+	let dat_count = u64::from_le_bytes(head[8..].try_into().unwrap()) as usize;
+	eyre::ensure!(dat_count == count, ".dat has {dat_count} entries, but .dir has {count}");
+
+	let timestamp = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)?
+		.as_secs() as u32;
+
+	// Resolve everything before touching the files, so that errors don't leave the archive half-written.
+	let mut files = Vec::new();
+	for file in &cmd.files {
+		let Some(name) = file.file_name().and_then(|a| a.to_str()) else {
+			eyre::bail!("{}: invalid filename", file.display());
+		};
+		let name = dirdat::normalize_name(name);
+		eyre::ensure!(dirdat::denormalize_name(&name).len() <= 12, "{name}: name does not fit in 8.3 format");
+
+		let index = entries.iter().position(|e| e.name == name);
+		let index = match (mode, index) {
+			(Mode::Add, Some(_)) => eyre::bail!("{name}: already exists in archive"),
+			(Mode::Add, None) if entries.len() == count => eyre::bail!("{name}: archive has no free slots"),
+			(Mode::Add, None) => {
+				entries.push(DirEntry {
+					name: name.clone(),
+					unk1: 0,
+					compressed_size: 0,
+					unk3: 0,
+					archived_size: 0,
+					timestamp: 0,
+					offset: 0,
+				});
+				entries.len() - 1
+			}
+			(Mode::Replace, Some(index)) => index,
+			(Mode::Replace, None) => eyre::bail!("{name}: does not exist in archive"),
+		};
+
+		let mut data = std::fs::read(file)?;
+		if !cmd.no_compress && dirdat::is_compressed(&name) {
+			data = decompress::compress_ed6_to_vec(&data);
+		}
+		files.push((index, data));
+	}
+
+	write_files(&mut dat, &mut entries, count, &files, timestamp)?;
+	let tmp = cmd.dir_file.with_extension("dir.tmp");
+	std::fs::write(&tmp, dirdat::write_dir(&entries, count)?)?;
+	std::fs::rename(&tmp, &cmd.dir_file)?;
+
+	for (index, _) in files {
+		let name = &entries[index].name;
+		if let Some(arch) = archive_number {
+			println!("0x{:04X}{:04X} {}", arch, index, name);
+		} else {
+			println!("0x{:04X} {}", index, name);
+		}
+	}
+	Ok(())
+}
+
+/// Writes the data to the .dat file, and updates `entries` to match. `count` is the number of slots
+/// in the archive.
+///
+/// The offset table is only updated after the data is written, so if appending is interrupted, the
+/// archive is left as it was.
+fn write_files(
+	dat: &mut File,
+	entries: &mut [DirEntry],
+	count: usize,
+	files: &[(usize, Vec<u8>)],
+	timestamp: u32,
+) -> eyre::Result<()> {
+	let mut table = vec![0; dirdat::data_start(count)];
+	dat.seek(SeekFrom::Start(0))?;
+	dat.read_exact(&mut table)?;
+	let mut slots = dirdat::read_dat(&table)?.into_iter().map(|d| d.offset).collect::<Vec<_>>();
+	slots.push(u32::from_le_bytes(table[table.len() - 4..].try_into().unwrap()) as usize);
+	let mut end = dat.seek(SeekFrom::End(0))? as usize;
+
+	for (index, data) in files {
+		let e = &mut entries[*index];
+		let space = e.range().filter(|r| data.len() <= r.len() || r.end >= end);
+		let offset = match space {
+			Some(r) => {
+				dat.seek(SeekFrom::Start(r.start as u64))?;
+				dat.write_all(data)?;
+				if r.end >= end {
+					end = r.start + data.len();
+					dat.set_len(end as u64)?;
+					e.archived_size = data.len();
+				} else {
+					dat.write_all(&vec![0; r.len() - data.len()])?;
+				}
+				r.start
+			}
+			None => {
+				let offset = end;
+				dat.seek(SeekFrom::Start(offset as u64))?;
+				dat.write_all(data)?;
+				end += data.len();
+				e.archived_size = data.len();
+				offset
+			}
+		};
+		e.compressed_size = data.len();
+		e.unk3 = data.len();
+		e.timestamp = timestamp;
+		e.offset = offset;
+		slots[*index] = offset;
+	}
+	slots[entries.len()] = end;
+
+	let mut table = Vec::with_capacity(4 * slots.len());
+	for slot in slots {
+		table.extend(u32::try_from(slot)?.to_le_bytes());
+	}
+	dat.seek(SeekFrom::Start(16))?;
+	dat.write_all(&table)?;
+	Ok(())
+}
+
+#[test]
+fn test() -> eyre::Result<()> {
+	let dir = std::env::temp_dir().join(format!("factoria-patch-{}", std::process::id()));
+	std::fs::create_dir_all(dir.join("new"))?;
+	let dir_file = dir.join("ED6_DT99.dir");
+
+	let entry = |name: &str| DirEntry { name: name.into(), unk1: 0, compressed_size: 4, unk3: 4, archived_size: 4, timestamp: 1234, offset: 0 };
+	let mut entries = vec![entry("a.txt"), entry("b.txt"), entry("c.txt")];
+	dirdat::layout(&mut entries, 5);
+	std::fs::write(&dir_file, dirdat::write_dir(&entries, 5)?)?;
+	std::fs::write(dir_file.with_extension("dat"), dirdat::write_dat(&entries, 5, &[b"aaaa", b"bbbb", b"cccc"])?)?;
+
+	let run = |mode: Mode, files: &[(&str, &[u8])]| {
+		let mut paths = Vec::new();
+		for (name, data) in files {
+			std::fs::write(dir.join("new").join(name), data)?;
+			paths.push(dir.join("new").join(name));
+		}
+		patch(&Patch { no_compress: true, dir_file: dir_file.clone(), files: paths }, mode)
+	};
+	// Fits in the old space, is appended, grows at the end, and new entries
+	run(Mode::Replace, &[("a.txt", b"AA")])?;
+	run(Mode::Replace, &[("b.txt", b"BBBBBBBB")])?;
+	run(Mode::Replace, &[("b.txt", b"BBBBBBBBBB")])?;
+	run(Mode::Add, &[("e.txt", b"eeeee"), ("d.txt", b"dd")])?;
+	assert!(run(Mode::Add, &[("f.txt", b"f")]).is_err());
+
+	let entries = dirdat::read_dir(&std::fs::read(&dir_file)?)?;
+	let dat = std::fs::read(dir_file.with_extension("dat"))?;
+	let mut problems = Vec::new();
+	crate::verify::check(&entries, entries.capacity(), &dat, false, &mut |name, msg| problems.push(format!("{name}: {msg}")))?;
+	assert_eq!(problems, Vec::<String>::new());
+
+	let expected: [(&str, &[u8]); 5] = [("a.txt", b"AA"), ("b.txt", b"BBBBBBBBBB"), ("c.txt", b"cccc"), ("e.txt", b"eeeee"), ("d.txt", b"dd")];
+	for e in &entries {
+		let (_, data) = expected.iter().find(|a| a.0 == e.name).unwrap();
+		assert_eq!(&dat[e.offset..e.offset + e.compressed_size], *data);
+	}
+	assert_eq!(entries.len(), 5);
+	// Only the grown entry moved, and its old space is left unused
+	assert_eq!(dat.len(), dirdat::data_start(5) + 4 + 4 + 4 + 10 + 5 + 2);
+
+	std::fs::remove_dir_all(&dir)?;
+	Ok(())
+}
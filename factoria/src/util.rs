@@ -4,3 +4,12 @@ pub fn mmap(path: &Path) -> std::io::Result<memmap2::Mmap> {
 	let file = std::fs::File::open(path)?;
 	unsafe { memmap2::Mmap::map(&file) }
 }
+
+pub fn get_archive_number(path: &Path) -> Option<u8> {
+	let name = path
+		.file_name()?
+		.to_str()?
+		.strip_prefix("ED6_DT")?
+		.strip_suffix(".dir")?;
+	u8::from_str_radix(name, 16).ok()
+}
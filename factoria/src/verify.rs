@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use clap::ValueHint;
+use themelios_archive::dirdat;
+
+use crate::util::mmap;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Verify {
+	/// Don't try to decompress compressed files.
+	///
+	/// This is a lot faster, but misses corrupted file data.
+	#[clap(long)]
+	no_decompress: bool,
+
+	/// The .dir files to check. The corresponding .dat files are checked too.
+	#[clap(value_hint = ValueHint::FilePath, required = true)]
+	dir_file: Vec<PathBuf>,
+}
+
+pub fn verify(cmd: &Verify) -> eyre::Result<()> {
+	let mut problems = 0;
+	for dir_file in &cmd.dir_file {
+		let mut report = |name: &str, msg: std::fmt::Arguments| {
+			println!("{}: {name}: {msg}", dir_file.display());
+			problems += 1;
+		};
+
+		let entries = dirdat::read_dir(&mmap(dir_file)?)?;
+		let dat = mmap(&dir_file.with_extension("dat"))?;
+		check(&entries, entries.capacity(), &dat, !cmd.no_decompress, &mut report)?;
+	}
+
+	eyre::ensure!(problems == 0, "found {problems} problems");
+	Ok(())
+}
+
+/// Checks an archive's entries against its .dat file, reporting any problems found. `count` is the
+/// number of entries in the .dir, including unused slots.
+pub fn check(
+	entries: &[dirdat::DirEntry],
+	count: usize,
+	dat: &[u8],
+	decompress: bool,
+	report: &mut impl FnMut(&str, std::fmt::Arguments),
+) -> eyre::Result<()> {
+	let dat_entries = dirdat::read_dat(dat)?;
+
+	if dat_entries.len() != count {
+		report("-", format_args!(".dat has {} entries, but .dir has {count}", dat_entries.len()));
+	}
+
+	let mut ranges = Vec::new();
+	for (e, d) in entries.iter().zip(&dat_entries) {
+		let Some(range) = e.range() else { continue };
+
+		if d.offset != e.offset {
+			report(&e.name, format_args!("offset is {:#X} in .dir, but {:#X} in .dat", e.offset, d.offset));
+		}
+		if e.archived_size < e.compressed_size {
+			report(&e.name, format_args!("archived size {:#X} is less than compressed size {:#X}", e.archived_size, e.compressed_size));
+		}
+		if range.end > dat.len() {
+			report(&e.name, format_args!("range {:#X}..{:#X} extends past end of .dat (size {:#X})", range.start, range.end, dat.len()));
+			continue
+		}
+		ranges.push((range.clone(), e.name.as_str()));
+
+		if decompress && dirdat::is_compressed(&e.name) {
+			let data = &dat[range.start..range.start + e.compressed_size.min(e.archived_size)];
+			if let Err(err) = decompress::decompress_ed6_from_slice(data) {
+				report(&e.name, format_args!("failed to decompress: {err}"));
+			}
+		}
+	}
+
+	ranges.sort_by_key(|(r, _)| (r.start, r.end));
+	let mut prev: Option<&(std::ops::Range<usize>, &str)> = None;
+	for cur @ (b, b_name) in ranges.iter().filter(|(r, _)| !r.is_empty()) {
+		match prev {
+			Some((a, a_name)) if b.start < a.end => {
+				report(b_name, format_args!("overlaps with {a_name} ({:#X}..{:#X} and {:#X}..{:#X})", a.start, a.end, b.start, b.end));
+				if b.end > a.end {
+					prev = Some(cur);
+				}
+			}
+			_ => prev = Some(cur),
+		}
+	}
+	Ok(())
+}
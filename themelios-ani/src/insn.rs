@@ -367,6 +367,22 @@ impl Insn {
 		introspect!(run);
 	}
 
+	/// Calls `f` on each address the instruction refers to.
+	pub fn visit_addrs(&mut self, mut f: impl FnMut(&mut Addr)) {
+		macro run {
+			([$(($ident:ident $(($_n:ident $($ty:tt)*))*))*]) => {
+				match self {
+					$(Self::$ident($($_n),*) => {
+						$(run!($_n $($ty)*);)*
+					})*
+				}
+			},
+			($v:ident Addr) => { f($v) },
+			($v:ident $($t:tt)*) => { let _ = $v; }
+		}
+		introspect!(run);
+	}
+
 	pub fn validate(game: Game, i: &Insn) -> Result<(), WriteError> {
 		let mut w = Writer::new();
 		Self::write(&mut w, game, i)
@@ -459,6 +475,20 @@ mod talk_random {
 	}
 
 	pub(super) fn write(f: &mut Writer, _: Game, v: &[String]) -> Result<(), WriteError> {
-		todo!()
+		// A trailing empty string means the list was not terminated, see above
+		match v.split_last() {
+			Some((last, rest)) if last.is_empty() => {
+				for s in rest {
+					f.string(s)?;
+				}
+			}
+			_ => {
+				for s in v {
+					f.string(s)?;
+				}
+				f.string("")?;
+			}
+		}
+		Ok(())
 	}
 }
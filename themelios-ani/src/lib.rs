@@ -31,20 +31,29 @@ impl std::fmt::Debug for Addr {
 }
 
 pub mod ed6 {
-	use std::collections::BTreeSet;
+	use std::collections::BTreeMap;
 
 	use themelios_common::util::*;
 	use gospel::read::{Reader, Le as _};
+	use gospel::write::{Writer, Le as _, Label};
 	use crate::types::*;
 	use crate::Addr;
+	use crate::insn::Insn;
 
+	#[derive(Debug, Clone, PartialEq, Eq)]
 	pub struct Ani {
 		pub chips: Vec<(FileId, FileId)>,
 		pub models: Vec<String>,
 		pub bones: Option<(u8, Vec<String>)>,
 		pub sprite_offsets: [(u8,u8); 8],
 		pub funcs: Vec<Addr>,
-		pub insns: Vec<(Addr, crate::insn::Insn)>,
+		/// The instructions, with their position in the file.
+		///
+		/// When writing, the positions are only used to resolve the addresses in `funcs` and in
+		/// the instructions, so they do not need to be accurate, only unique. A few files have
+		/// addresses pointing into the middle of an instruction; these are kept at the same offset
+		/// into that instruction.
+		pub insns: Vec<(Addr, Insn)>,
 	}
 
 	pub fn read_monster(game: Game, data: &[u8]) -> Result<Ani, ReadError> {
@@ -82,41 +91,18 @@ pub mod ed6 {
 		let mut insns = Vec::new();
 		while !f.is_empty() {
 			let p = f.pos();
-			let i = crate::insn::Insn::read(&mut f, game)?;
+			let i = Insn::read(&mut f, game)?;
 			insns.push((Addr(p), i));
 		}
 
-		let mut xs = BTreeSet::from_iter(funcs.iter().copied());
-		for i in &insns {
-			macro run {
-				([$(($ident:ident $(($_n:ident $($ty:tt)*))*))*]) => {
-					match &i.1 {
-						$(crate::insn::Insn::$ident($($_n),*) => {
-							$(run!($_n $($ty)*);)*
-						})*
-					}
-				},
-				($v:ident Addr) => { xs.insert(*$v) },
-				($i:ident $($t:tt)*) => {}
-			}
-			crate::insn::introspect!(run);
-		}
-
-		println!("chips: {:?}", chips);
-		println!("bones: {:?}", bones);
-		println!("offsets: {:?}", sprite_offsets);
-		println!("funcs: {:?}", funcs);
-		for (i, p) in &insns {
-			if xs.remove(i) {
-				print!("  {i:?} ");
-			} else {
-				print!("        ");
-			}
-			println!("{p:?}");
+		// Addresses may point into the middle of an instruction, but not outside the code
+		let start = insns.first().map_or(data.len(), |a| a.0.0);
+		let mut addrs = funcs.clone();
+		for (_, insn) in &insns {
+			insn.clone().visit_addrs(|a| addrs.push(*a));
 		}
-
-		if !xs.is_empty() {
-			println!("ERROR: {xs:?}");
+		for a in addrs {
+			ensure!((start..data.len()).contains(&a.0), "address {a:?} is outside the code");
 		}
 
 		Ok(Ani {
@@ -129,44 +115,146 @@ pub mod ed6 {
 		})
 	}
 
+	pub fn write_monster(game: Game, ani: &Ani) -> Result<Vec<u8>, WriteError> {
+		let mut f = Writer::new();
+		let l_func_table = Label::new();
+		let l_sprite_offsets = Label::new();
+		f.delay16(l_func_table);
+		f.delay16(l_sprite_offsets);
+		let l_bones = ani.bones.as_ref().map(|_| Label::new());
+		match l_bones {
+			Some(l) => f.delay16(l),
+			None => f.u16(0),
+		}
+
+		for (ch, cp) in &ani.chips {
+			ensure!(ch.0 != 0xFFFFFFFF, "invalid chip id");
+			f.u32(ch.0);
+			f.u32(cp.0);
+		}
+		f.u32(0xFFFFFFFF);
+
+		for model in &ani.models {
+			ensure!(!model.is_empty(), "model names cannot be empty");
+			f.string(model)?;
+		}
+		f.string("")?;
+
+		if let (Some((x, bones)), Some(l)) = (&ani.bones, l_bones) {
+			f.label(l);
+			f.u8(*x);
+			for bone in bones {
+				ensure!(!bone.is_empty(), "bone names cannot be empty");
+				f.string(bone)?;
+			}
+			f.string("")?;
+		}
+
+		// The code comes right after the fixed-size tables, so the positions can be calculated in advance
+		let mut pos = f.len() + 2 * ani.funcs.len() + 16;
+		let mut addrs = BTreeMap::new();
+		for (addr, insn) in &ani.insns {
+			// The addresses are not resolved yet, but they do not affect the size
+			let mut insn = insn.clone();
+			insn.visit_addrs(|a| *a = Addr(0));
+			let mut g = Writer::new();
+			Insn::write(&mut g, game, &insn)?;
+			ensure!(addrs.insert(*addr, (Addr(pos), g.len())).is_none(), "duplicate address {addr:?}");
+			pos += g.len();
+		}
+		let addr = |a: &Addr| match addrs.range(..=*a).next_back() {
+			Some((start, (new, len))) if a.0 - start.0 < (*len).max(1) => Ok(Addr(new.0 + a.0 - start.0)),
+			_ => Err(format!("address {a:?} is not in an instruction")),
+		};
+
+		f.label(l_func_table);
+		for func in &ani.funcs {
+			f.u16(cast(addr(func)?.0)?);
+		}
+
+		f.label(l_sprite_offsets);
+		for (a, b) in ani.sprite_offsets {
+			f.u8(a);
+			f.u8(b);
+		}
+
+		for (_, insn) in &ani.insns {
+			let mut insn = insn.clone();
+			let mut err = None;
+			insn.visit_addrs(|a| match addr(a) {
+				Ok(v) => *a = v,
+				Err(e) => err = Some(e),
+			});
+			if let Some(e) = err {
+				bail!(e.into());
+			}
+			Insn::write(&mut f, game, &insn)?;
+		}
+
+		Ok(f.finish()?)
+	}
+
 	#[test]
 	fn test() -> Result<(), Box<dyn std::error::Error>> {
 		let mut i = std::fs::read_dir("../data/fc.extract/10/")?.collect::<Result<Vec<_>, _>>()?;
 		i.sort_by_key(|a| a.path());
-		println!("FC");
 		for file in i {
 			let p = file.path();
 			let n = p.file_name().unwrap().to_str().unwrap();
 			if n.starts_with("as") && !n.starts_with("asmag") && !n.starts_with("asitem") {
-				println!("\n{n}");
-				read_monster(Game::Fc, &std::fs::read(p)?)?;
+				check(Game::Fc, &std::fs::read(p)?)?;
 			}
 		}
 
-		println!("SC");
 		let mut i = std::fs::read_dir("../data/sc.extract/30/")?.collect::<Result<Vec<_>, _>>()?;
 		i.sort_by_key(|a| a.path());
 		for file in i {
 			let p = file.path();
 			let n = p.file_name().unwrap().to_str().unwrap();
 			if n.starts_with("as") && !n.starts_with("asmag") && !n.starts_with("asitem") {
-				println!("\n{n}");
-				read_monster(Game::Sc, &std::fs::read(p)?)?;
+				check(Game::Sc, &std::fs::read(p)?)?;
 			}
 		}
 
-		println!("3rd");
 		let mut i = std::fs::read_dir("../data/3rd.extract/30/")?.collect::<Result<Vec<_>, _>>()?;
 		i.sort_by_key(|a| a.path());
 		for file in i {
 			let p = file.path();
 			let n = p.file_name().unwrap().to_str().unwrap();
 			if n.starts_with("as") && !n.starts_with("asmag") && !n.starts_with("asitem") {
-				println!("\n{n}");
-				read_monster(Game::Tc, &std::fs::read(p)?)?;
+				check(Game::Tc, &std::fs::read(p)?)?;
 			}
 		}
 
+		fn check(game: Game, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+			let ani = read_monster(game, data)?;
+			assert_eq!(write_monster(game, &ani)?, data);
+			Ok(())
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_inside() -> Result<(), Box<dyn std::error::Error>> {
+		// A jump into the middle of an instruction should stay in the middle of it
+		let ani = Ani {
+			chips: Vec::new(),
+			models: vec!["model".to_owned()],
+			bones: None,
+			sprite_offsets: [(0, 0); 8],
+			funcs: vec![Addr(0)],
+			insns: vec![
+				(Addr(0), Insn::Goto(Addr(11))),
+				(Addr(10), Insn::Goto(Addr(0))),
+				(Addr(20), Insn::End()),
+			],
+		};
+		let data = write_monster(Game::Fc, &ani)?;
+		let ani = read_monster(Game::Fc, &data)?;
+		let (Insn::Goto(a), (b, _)) = (&ani.insns[0].1, &ani.insns[1]) else { panic!() };
+		assert_eq!(a.0, b.0 + 1);
+		assert_eq!(write_monster(Game::Fc, &ani)?, data);
 		Ok(())
 	}
 }
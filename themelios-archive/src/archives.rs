@@ -0,0 +1,169 @@
+//! Read access to all the archives of an ED6 PC installation.
+use std::borrow::Cow;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use crate::dirdat::{self, DirEntry};
+use crate::lookup::{ED6Lookup, Lookup};
+
+struct Archive {
+	entries: Vec<DirEntry>,
+	dat: memmap2::Mmap,
+}
+
+/// The `ED6_DTxx.dir/.dat` archives of a game, allowing files to be looked up by file id or name.
+///
+/// The .dat files are memory-mapped rather than read, so opening this is cheap, but the files must
+/// not be modified while it is open.
+///
+/// The Evolution versions do not use archives, so this only works for the PC versions.
+pub struct Archives {
+	archives: Box<[Option<Archive>; 64]>,
+	lookup: ED6Lookup,
+}
+
+impl std::fmt::Debug for Archives {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let mut d = f.debug_struct("Archives");
+		for (n, x) in self.archives.iter().enumerate() {
+			if let Some(x) = x {
+				d.field(&format!("ED6_DT{n:02X}"), &format_args!("{} entries", x.entries.len()));
+			}
+		}
+		d.finish()
+	}
+}
+
+impl Archives {
+	/// Opens all archives in the given directory.
+	///
+	/// `dir` should be a directory containing the `ED6_DTxx.dir/.dat` files, same as for [`ED6Lookup::for_pc`].
+	pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+		let dir = dir.as_ref();
+		dir.read_dir()?;
+		let mut archives = Box::new([(); 64].map(|_| None));
+		let mut names = [(); 64].map(|_| Vec::new());
+		for (n, (archive, names)) in archives.iter_mut().zip(&mut names).enumerate() {
+			let Ok(data) = std::fs::read(dir.join(format!("ED6_DT{n:02X}.dir"))) else { continue };
+			let entries = dirdat::read_dir(&data)
+				.map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+			let file = std::fs::File::open(dir.join(format!("ED6_DT{n:02X}.dat")))?;
+			let dat = unsafe { memmap2::Mmap::map(&file)? };
+			*names = entries.iter().map(|e| ED6Lookup::pc_name(n, &e.name)).collect();
+			*archive = Some(Archive { entries, dat });
+		}
+		Ok(Archives {
+			archives,
+			lookup: ED6Lookup::new(names),
+		})
+	}
+
+	/// The lookup used for resolving names, identical to the one returned by [`ED6Lookup::for_pc`].
+	pub fn lookup(&self) -> &ED6Lookup {
+		&self.lookup
+	}
+
+	/// Returns the entries in the given archive, or `None` if there is no such archive.
+	pub fn entries(&self, archive: u8) -> Option<&[DirEntry]> {
+		Some(&self.archives.get(archive as usize)?.as_ref()?.entries)
+	}
+
+	/// Returns the .dir entry for a file id.
+	pub fn entry(&self, id: u32) -> Option<&DirEntry> {
+		self.entries((id >> 16) as u8)?.get((id & 0xFFFF) as usize)
+	}
+
+	/// Returns the data for a file id, exactly as it is stored in the archive.
+	///
+	/// Returns [`ErrorKind::NotFound`] if there is no such file, or if it has been deleted.
+	pub fn get_raw(&self, id: u32) -> Result<&[u8]> {
+		let not_found = || Error::new(ErrorKind::NotFound, format!("no file with id 0x{id:08X}"));
+		let archive = self.archives.get((id >> 16) as usize)
+			.and_then(|a| a.as_ref())
+			.ok_or_else(not_found)?;
+		let e = archive.entries.get((id & 0xFFFF) as usize).ok_or_else(not_found)?;
+		let range = e.range().ok_or_else(|| Error::new(ErrorKind::NotFound, format!("{} has been deleted", e.name)))?;
+		let data = archive.dat.get(range).ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("{} extends past end of archive", e.name)))?;
+		Ok(&data[..e.compressed_size.min(data.len())])
+	}
+
+	/// Returns the data for a file id, decompressing it if necessary.
+	///
+	/// Whether a file is compressed is determined by [`dirdat::is_compressed`].
+	pub fn get(&self, id: u32) -> Result<Cow<'_, [u8]>> {
+		let data = self.get_raw(id)?;
+		if self.entry(id).is_some_and(|e| dirdat::is_compressed(&e.name)) {
+			decompress::decompress_ed6_from_slice(data)
+				.map(Cow::Owned)
+				.map_err(|e| Error::new(ErrorKind::InvalidData, e))
+		} else {
+			Ok(Cow::Borrowed(data))
+		}
+	}
+
+	/// Resolves a name, as given by [`lookup`](Self::lookup), to a file id.
+	pub fn index(&self, name: &str) -> Result<u32> {
+		self.lookup.index(name)
+			.ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no file named {name:?}")))
+	}
+
+	/// Shorthand for `self.get_raw(self.index(name)?)`.
+	pub fn get_raw_by_name(&self, name: &str) -> Result<&[u8]> {
+		self.get_raw(self.index(name)?)
+	}
+
+	/// Shorthand for `self.get(self.index(name)?)`.
+	pub fn get_by_name(&self, name: &str) -> Result<Cow<'_, [u8]>> {
+		self.get(self.index(name)?)
+	}
+}
+
+#[test]
+fn test() -> std::result::Result<(), Box<dyn std::error::Error>> {
+	let dir = std::env::temp_dir().join(format!("themelios-archives-{}", std::process::id()));
+	std::fs::create_dir_all(&dir)?;
+
+	let text = b"scena scena scena scena".repeat(4);
+	let sn = decompress::compress_ed6_to_vec(&text);
+	let write = |n: u8, files: &[(&str, &[u8])]| -> std::result::Result<(), Box<dyn std::error::Error>> {
+		let mut entries = files.iter().map(|(name, data)| DirEntry {
+			name: name.to_string(),
+			unk1: 0,
+			compressed_size: data.len(),
+			unk3: data.len(),
+			archived_size: data.len(),
+			// Deleted entries have no timestamp
+			timestamp: if name.starts_with("gone") { 0 } else { 1234 },
+			offset: 0,
+		}).collect::<Vec<_>>();
+		dirdat::layout(&mut entries, 4);
+		let data = files.iter().map(|a| a.1).collect::<Vec<_>>();
+		std::fs::write(dir.join(format!("ED6_DT{n:02X}.dir")), dirdat::write_dir(&entries, 4)?)?;
+		std::fs::write(dir.join(format!("ED6_DT{n:02X}.dat")), dirdat::write_dat(&entries, 4, &data)?)?;
+		Ok(())
+	};
+	write(0x01, &[("t0100._sn", &sn), ("gone._ch", b""), ("a.txt", b"hello")])?;
+	write(0x06, &[("ch00000._ch", b"chip")])?;
+
+	let archives = Archives::new(&dir)?;
+	assert_eq!(archives.get_raw(0x00010000)?, &sn[..]);
+	assert_eq!(archives.get(0x00010000)?, &text[..]);
+	assert_eq!(archives.get(0x00010002)?, &b"hello"[..]);
+	assert_eq!(archives.get_by_name("a.txt")?, &b"hello"[..]);
+	assert_eq!(archives.get_raw_by_name("apl/ch00000._ch")?, b"chip");
+	assert_eq!(archives.index("t0100._sn")?, 0x00010000);
+
+	// Deleted entries still have a .dir entry, but no data
+	assert_eq!(archives.entry(0x00010001).map(|e| e.name.as_str()), Some("gone._ch"));
+	assert_eq!(archives.get(0x00010001).unwrap_err().kind(), ErrorKind::NotFound);
+
+	// Past the end of an archive, in a missing archive, and past the last possible archive
+	for id in [0x00010003, 0x00020000, 0x00400000, 0xFFFFFFFF] {
+		assert!(archives.entry(id).is_none());
+		assert_eq!(archives.get(id).unwrap_err().kind(), ErrorKind::NotFound);
+	}
+	assert_eq!(archives.get_by_name("missing._sn").unwrap_err().kind(), ErrorKind::NotFound);
+
+	std::fs::remove_dir_all(&dir)?;
+	Ok(())
+}
@@ -1,9 +1,8 @@
-//! Utilities for reading ED6 PC's .dir/.dat archives.
-//!
-//! There is currently no support for writing archives; this may be added later.
+//! Utilities for reading and writing ED6 PC's .dir/.dat archives.
 use std::ops::Range;
 
 use gospel::read::{Reader, Le as _};
+use gospel::write::{Writer, Le as _};
 
 /// An entry in a .dir file,
 ///
@@ -120,6 +119,123 @@ pub fn read_dat(data: &[u8]) -> Result<Vec<DatEntry>, gospel::read::Error> {
 	Ok(items)
 }
 
+/// Writes a .dir file.
+///
+/// `count` is the total number of entries in the file, and any entries beyond `entries.len()` are
+/// written as `/_______.___`. This is usually the capacity of the Vec returned by [`read_dir`].
+///
+/// Names are converted back into the uppercase 8.3 format by [`denormalize_name`].
+pub fn write_dir(entries: &[DirEntry], count: usize) -> Result<Vec<u8>, gospel::write::Error> {
+	let mut f = Writer::new();
+	f.slice(b"LB DIR\x1A\0");
+	f.u64(count.max(entries.len()) as u64);
+
+	for e in entries {
+		let pos = f.len();
+		let other = |source: Box<dyn std::error::Error + Send + Sync>| gospel::write::Error::Other { pos, source };
+		let name = cp932::encode(&denormalize_name(&e.name))
+			.map_err(|_| other(format!("cannot encode {:?}", e.name).into()))?;
+		if name.len() > 12 {
+			return Err(other(format!("name {:?} is too long", e.name).into()))
+		}
+		f.slice(&name);
+		f.slice(&[b' '; 12][name.len()..]);
+		f.u32(e.unk1);
+		f.u32(u32_(pos, e.compressed_size)?);
+		f.u32(u32_(pos, e.unk3)?);
+		f.u32(u32_(pos, e.archived_size)?);
+		f.u32(e.timestamp);
+		f.u32(u32_(pos, e.offset)?);
+	}
+
+	for _ in entries.len()..count {
+		f.slice(b"/_______.___");
+		f.slice(&[0; 24]);
+	}
+
+	f.finish()
+}
+
+/// Writes a .dat file.
+///
+/// `data` contains the data for each entry, and must have the same length as `entries`. Each entry's
+/// data is placed at its `offset`, and followed by null bytes up to its `archived_size`. Any gaps
+/// between entries are also filled with null bytes. Deleted entries, as determined by
+/// [`DirEntry::range`], have no data. `count` should be the same as for [`write_dir`].
+///
+/// To place the entries one after another, use [`layout`] first.
+pub fn write_dat(entries: &[DirEntry], count: usize, data: &[impl AsRef<[u8]>]) -> Result<Vec<u8>, gospel::write::Error> {
+	assert_eq!(entries.len(), data.len());
+	let count = count.max(entries.len());
+
+	let mut f = Writer::new();
+	f.slice(b"LB DAT\x1A\0");
+	f.u64(count as u64);
+
+	let end = data_start(count).max(entries.iter().filter_map(|e| e.range()).map(|r| r.end).max().unwrap_or(0));
+	for i in 0..=count {
+		let pos = f.len();
+		match entries.get(i) {
+			Some(e) => f.u32(u32_(pos, e.offset)?),
+			None if i == entries.len() => f.u32(u32_(pos, end)?),
+			None => f.u32(0),
+		}
+	}
+
+	let mut order = std::iter::zip(entries, data)
+		.filter(|(e, _)| e.range().is_some())
+		.collect::<Vec<_>>();
+	order.sort_by_key(|(e, _)| e.offset);
+	for (e, data) in order {
+		let data = data.as_ref();
+		let pos = f.len();
+		let other = |source: String| gospel::write::Error::Other { pos, source: source.into() };
+		if data.len() != e.compressed_size {
+			return Err(other(format!("{}: expected {} bytes of data, got {}", e.name, e.compressed_size, data.len())))
+		}
+		if e.archived_size < e.compressed_size {
+			return Err(other(format!("{}: archived size {} is smaller than compressed size {}", e.name, e.archived_size, e.compressed_size)))
+		}
+		if e.offset < f.len() {
+			return Err(other(format!("{}: offset {:#X} overlaps previous data", e.name, e.offset)))
+		}
+		f.slice(&vec![0; e.offset - f.len()]);
+		f.slice(data);
+		f.slice(&vec![0; e.archived_size - e.compressed_size]);
+	}
+
+	f.finish()
+}
+
+/// Sets the offsets of the entries so that they are placed one after another in the .dat file.
+///
+/// `archived_size` is raised to `compressed_size` if it is smaller, but is otherwise kept as is.
+/// Deleted entries take up no space.
+pub fn layout(entries: &mut [DirEntry], count: usize) {
+	let mut pos = data_start(count.max(entries.len()));
+	for e in entries {
+		e.offset = pos;
+		if e.range().is_some() {
+			e.archived_size = e.archived_size.max(e.compressed_size);
+			pos += e.archived_size;
+		}
+	}
+}
+
+/// The offset in a .dat file with the given number of entries where the data starts.
+pub fn data_start(count: usize) -> usize {
+	16 + 4 * (count + 1)
+}
+
+fn u32_(pos: usize, v: usize) -> Result<u32, gospel::write::Error> {
+	u32::try_from(v).map_err(|e| gospel::write::Error::Other { pos, source: e.into() })
+}
+
+/// Files with an extension starting with an underscore, such as `._sn` or `._ch`, are compressed.
+pub fn is_compressed(name: &str) -> bool {
+	name.rsplit_once('.').is_some_and(|(_, ext)| ext.starts_with('_'))
+}
+
 pub fn normalize_name(name: &str) -> String {
 	let name = name.to_lowercase();
 	if let Some((name, ext)) = name.split_once('.') {
@@ -128,3 +244,43 @@ pub fn normalize_name(name: &str) -> String {
 		name
 	}
 }
+
+/// The inverse of [`normalize_name`]: converts a name to the uppercase 8.3 format used in .dir files.
+pub fn denormalize_name(name: &str) -> String {
+	let name = name.to_uppercase();
+	let name = if let Some((name, ext)) = name.split_once('.') {
+		format!("{name:<8}.{ext}")
+	} else {
+		name
+	};
+	format!("{name:<12}")
+}
+
+#[test]
+fn test_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+	let mut entries = vec![
+		DirEntry { name: "t0100._sn".into(), unk1: 0, compressed_size: 5, unk3: 5, archived_size: 5, timestamp: 1234, offset: 0 },
+		DirEntry { name: "t_world._dt".into(), unk1: 7, compressed_size: 3, unk3: 1024, archived_size: 8, timestamp: 5678, offset: 0 },
+		DirEntry { name: "gone._ch".into(), unk1: 0, compressed_size: 0, unk3: 0, archived_size: 0, timestamp: 0, offset: 0 },
+	];
+	let data: [&[u8]; 3] = [b"hello", b"abc", b""];
+	layout(&mut entries, 5);
+
+	let dir = write_dir(&entries, 5)?;
+	let dat = write_dat(&entries, 5, &data)?;
+	assert_eq!(&dir[16..28], b"T0100   ._SN");
+
+	let entries2 = read_dir(&dir)?;
+	assert_eq!(entries2, entries);
+	assert_eq!(entries2.capacity(), 5);
+	assert_eq!(write_dir(&entries2, entries2.capacity())?, dir);
+
+	let dat_entries = read_dat(&dat)?;
+	assert_eq!(dat_entries.len(), 5);
+	for (e, d) in std::iter::zip(&entries2[..2], data) {
+		assert_eq!(&dat[e.range().unwrap()][..e.compressed_size], d);
+	}
+	assert_eq!(dat_entries[1].range(), entries2[1].range());
+	assert_eq!(dat_entries[3].range(), None);
+	Ok(())
+}
@@ -3,4 +3,6 @@
 
 pub mod lookup;
 pub mod dirdat;
-pub use lookup::{Lookup, ED6Lookup, ED7Lookup, NullLookup};
+pub mod archives;
+pub use lookup::{Lookup, ED6Lookup, ED7Lookup, LayeredLookup, NullLookup};
+pub use archives::Archives;
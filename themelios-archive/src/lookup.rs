@@ -2,6 +2,8 @@
 
 mod ed6;
 pub use ed6::ED6Lookup;
+mod layered;
+pub use layered::LayeredLookup;
 
 /// The main lookup trait.
 pub trait Lookup {
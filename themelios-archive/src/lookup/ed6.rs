@@ -30,10 +30,15 @@ impl ED6Lookup {
 	///
 	/// The reason for the `64` size is that that's how many archives the games load.
 	/// Any indices above that would just segfault, so they are not supported.
+	///
+	/// Empty names are treated as absent.
 	pub fn new(name: [Vec<String>; 64]) -> Self {
 		let mut index = HashMap::new();
 		for (n, x) in name.iter().enumerate() {
 			for (i, v) in x.iter().enumerate() {
+				if v.is_empty() {
+					continue
+				}
 				index.insert(v.clone(), (n << 16) as u32 | i as u32);
 			}
 		}
@@ -49,7 +54,8 @@ impl ED6Lookup {
 impl super::Lookup for ED6Lookup {
 	fn name(&self, index: u32) -> Option<String> {
 		let (arch, index) = (index >> 16, index & 0xFFFF);
-		Some(self.name.get(arch as usize)?.get(index as usize)?.clone())
+		let name = self.name.get(arch as usize)?.get(index as usize)?;
+		(!name.is_empty()).then(|| name.clone())
 	}
 
 	fn index(&self, name: &str) -> Option<u32> {
@@ -76,20 +82,25 @@ impl ED6Lookup {
 				.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
 				.into_iter()
 				.filter(|a| a.name != "/_______.___")
-				.map(|a| match n {
-					0x06 => format!("apl/{}", a.name),
-					0x26 => format!("apl2/{}", a.name),
-					0x07 => format!("npl/{}", a.name),
-					0x27 => format!("npl2/{}", a.name),
-					0x09 => format!("mons/{}", a.name),
-					0x29 => format!("mons2/{}", a.name),
-					_ => a.name,
-				})
+				.map(|a| Self::pc_name(n, &a.name))
 			.collect();
 		}
 		Ok(ED6Lookup::new(x))
 	}
 
+	/// The name that [`for_pc`](Self::for_pc) uses for the given entry of archive number `n`.
+	pub(crate) fn pc_name(n: usize, name: &str) -> String {
+		match n {
+			0x06 => format!("apl/{name}"),
+			0x26 => format!("apl2/{name}"),
+			0x07 => format!("npl/{name}"),
+			0x27 => format!("npl2/{name}"),
+			0x09 => format!("mons/{name}"),
+			0x29 => format!("mons2/{name}"),
+			_ => name.to_owned(),
+		}
+	}
+
 	/// Loads the indexes for the PSVita (Evolution) versions of the games.
 	///
 	/// `dir` should be the data directory extracted from `data.psarc`, normally named `data`, `data_sc`, or `data_3rd`.
@@ -197,3 +208,73 @@ impl ED6Lookup {
 		f.finish()
 	}
 }
+
+/// Plain text reading and writing.
+///
+/// The text format has one line per file, consisting of the archive number and index in hex,
+/// followed by the name, such as `01 0000 t0100._sn`. Blank lines and lines starting with `#` are
+/// ignored. This is easier to edit by hand than .ed6i, and works better with version control.
+impl ED6Lookup {
+	pub fn read_txt(text: &str) -> std::io::Result<Self> {
+		let mut x = [(); 64].map(|_| Vec::new());
+		for (lineno, line) in text.lines().enumerate() {
+			let err = |msg: &str| std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("line {}: {msg}", lineno + 1),
+			);
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue
+			}
+			// The name is the rest of the line, since it might contain spaces
+			let fields = line.split_once(char::is_whitespace)
+				.and_then(|(arch, rest)| Some((arch, rest.trim_start().split_once(char::is_whitespace)?)));
+			let Some((arch, (index, name))) = fields else {
+				return Err(err("expected archive, index, and name"))
+			};
+			let arch = usize::from_str_radix(arch, 16).map_err(|_| err("invalid archive number"))?;
+			// File ids only have 16 bits for the index
+			let index = u16::from_str_radix(index, 16).map_err(|_| err("invalid index"))? as usize;
+			let name = name.trim();
+			let names: &mut Vec<String> = x.get_mut(arch).ok_or_else(|| err("archive number out of range"))?;
+			if names.len() <= index {
+				names.resize(index + 1, String::new());
+			}
+			if !names[index].is_empty() {
+				return Err(err("duplicate entry"))
+			}
+			names[index] = name.to_owned();
+		}
+		Ok(Self::new(x))
+	}
+
+	pub fn write_txt(&self) -> String {
+		let mut s = String::new();
+		for (n, x) in self.name.iter().enumerate() {
+			for (i, v) in x.iter().enumerate() {
+				if !v.is_empty() {
+					s.push_str(&format!("{n:02X} {i:04X} {v}\n"));
+				}
+			}
+		}
+		s
+	}
+}
+
+#[test]
+fn test_txt() -> Result<(), Box<dyn std::error::Error>> {
+	use super::Lookup;
+	let text = "# comment\n01 0000 t0100._sn\n\n06 0002 apl/ch00000._ch\n";
+	let lookup = ED6Lookup::read_txt(text)?;
+	assert_eq!(lookup.name(0x00010000).as_deref(), Some("t0100._sn"));
+	assert_eq!(lookup.index("apl/ch00000._ch"), Some(0x00060002));
+	assert_eq!(lookup.name(0x00060001), None);
+	assert_eq!(lookup.write_txt(), "01 0000 t0100._sn\n06 0002 apl/ch00000._ch\n");
+	assert!(ED6Lookup::read_txt("40 0000 foo").is_err());
+	assert!(ED6Lookup::read_txt("01 FFFFFFFF foo").is_err());
+	let lookup = ED6Lookup::read_txt("01  0000 t0100._sn\n01\t0001\tt0101._sn\n")?;
+	assert_eq!(lookup.name(0x00010000).as_deref(), Some("t0100._sn"));
+	assert_eq!(lookup.name(0x00010001).as_deref(), Some("t0101._sn"));
+	assert!(ED6Lookup::read_txt("01 0000 a\n01 0000 b").is_err());
+	Ok(())
+}
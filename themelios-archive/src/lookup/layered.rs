@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{Lookup, ED6Lookup};
+
+/// An [`ED6Lookup`] with modifications on top.
+///
+/// This is mainly for mods that add new files to the archives, or rename existing ones, so that
+/// these names can be used in scripts without having to regenerate the whole lookup.
+#[derive(Debug, Clone)]
+pub struct LayeredLookup {
+	base: ED6Lookup,
+	name: BTreeMap<u32, String>,
+	index: HashMap<String, u32>,
+}
+
+impl From<ED6Lookup> for LayeredLookup {
+	fn from(base: ED6Lookup) -> Self {
+		Self::new(base)
+	}
+}
+
+impl LayeredLookup {
+	pub fn new(base: ED6Lookup) -> Self {
+		Self {
+			base,
+			name: BTreeMap::new(),
+			index: HashMap::new(),
+		}
+	}
+
+	/// The lookup that the modifications are applied to.
+	pub fn base(&self) -> &ED6Lookup {
+		&self.base
+	}
+
+	/// Sets the name of a file id, either adding a new file or renaming an existing one.
+	///
+	/// Returns the previous name of the file, if any.
+	///
+	/// # Panics
+	/// If the archive number is 64 or above; see [`ED6Lookup::new`].
+	pub fn insert(&mut self, id: u32, name: impl Into<String>) -> Option<String> {
+		assert!(id >> 16 < 64, "archive number out of range: 0x{id:08X}");
+		let name = name.into();
+		let prev = self.name(id);
+		if let Some(prev) = self.name.insert(id, name.clone()) {
+			self.index.remove(&prev);
+		}
+		self.index.insert(name, id);
+		prev
+	}
+
+	/// Inserts every name in another lookup, such as a list of files added by a mod.
+	pub fn extend(&mut self, other: &ED6Lookup) {
+		for (arch, names) in other.names().iter().enumerate() {
+			for (index, name) in names.iter().enumerate() {
+				if !name.is_empty() {
+					self.insert((arch as u32) << 16 | index as u32, name.clone());
+				}
+			}
+		}
+	}
+
+	/// The modifications made with [`insert`](Self::insert), in file id order.
+	pub fn changes(&self) -> impl Iterator<Item=(u32, &str)> + '_ {
+		self.name.iter().map(|(k, v)| (*k, v.as_str()))
+	}
+
+	/// Merges the modifications into a single [`ED6Lookup`], for example for writing to .ed6i.
+	///
+	/// If files are added past the end of an archive, the gap is filled with empty names, which
+	/// are treated as absent.
+	pub fn to_ed6lookup(&self) -> ED6Lookup {
+		let mut names = self.base.names().clone();
+		for (&id, name) in &self.name {
+			let names = &mut names[(id >> 16) as usize];
+			let index = (id & 0xFFFF) as usize;
+			if names.len() <= index {
+				names.resize(index + 1, String::new());
+			}
+			names[index] = name.clone();
+		}
+		ED6Lookup::new(names)
+	}
+
+	/// Shorthand for `self.to_ed6lookup().write_ed6i()`.
+	pub fn write_ed6i(&self) -> Result<Vec<u8>, gospel::write::Error> {
+		self.to_ed6lookup().write_ed6i()
+	}
+}
+
+impl Lookup for LayeredLookup {
+	fn name(&self, index: u32) -> Option<String> {
+		match self.name.get(&index) {
+			Some(name) => Some(name.clone()),
+			None => self.base.name(index),
+		}
+	}
+
+	fn index(&self, name: &str) -> Option<u32> {
+		if let Some(&index) = self.index.get(name) {
+			return Some(index)
+		}
+		// Don't resolve names that have been renamed away
+		let index = self.base.index(name)?;
+		(!self.name.contains_key(&index)).then_some(index)
+	}
+}
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	let mut names = [(); 64].map(|_| Vec::new());
+	names[1] = vec!["t0100._sn".to_owned(), "t0101._sn".to_owned()];
+	let mut lookup = LayeredLookup::new(ED6Lookup::new(names));
+
+	assert_eq!(lookup.insert(0x00010001, "mod0001._sn"), Some("t0101._sn".to_owned()));
+	assert_eq!(lookup.insert(0x00010004, "mod0002._sn"), None);
+	assert_eq!(lookup.name(0x00010000).as_deref(), Some("t0100._sn"));
+	assert_eq!(lookup.name(0x00010001).as_deref(), Some("mod0001._sn"));
+	assert_eq!(lookup.index("t0101._sn"), None);
+	assert_eq!(lookup.index("mod0002._sn"), Some(0x00010004));
+
+	let mut names = [(); 64].map(|_| Vec::new());
+	names[1] = vec![String::new(), "mod0003._sn".to_owned()];
+	lookup.extend(&ED6Lookup::new(names));
+	assert_eq!(lookup.name(0x00010000).as_deref(), Some("t0100._sn"));
+	assert_eq!(lookup.name(0x00010001).as_deref(), Some("mod0003._sn"));
+	assert_eq!(lookup.index("mod0001._sn"), None);
+
+	let flat = ED6Lookup::read_ed6i(&lookup.write_ed6i()?)?;
+	assert_eq!(flat.names()[1].len(), 5);
+	assert_eq!(flat.name(0x00010002), None);
+	assert_eq!(flat.index(""), None);
+	assert_eq!(flat.index("mod0002._sn"), Some(0x00010004));
+	Ok(())
+}
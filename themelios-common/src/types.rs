@@ -250,3 +250,39 @@ impl Game {
 		matches!(self.base(), BaseGame::Zero|BaseGame::Ao)
 	}
 }
+
+/// Allows using `Game` directly as a command line argument, with names such as `fc` and `sc_e`.
+#[cfg(feature = "clap")]
+impl clap::ValueEnum for Game {
+	fn value_variants<'a>() -> &'a [Self] {
+		use Game::*;
+		&[
+			Fc, FcEvo, FcKai,
+			Sc, ScEvo, ScKai,
+			Tc, TcEvo, TcKai,
+			Zero, ZeroEvo, ZeroKai,
+			Ao, AoEvo, AoKai,
+		]
+	}
+
+	fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+		use Game::*;
+		Some(clap::builder::PossibleValue::new(match self {
+			Fc      => "fc",
+			FcEvo   => "fc_e",
+			FcKai   => "fc_k",
+			Sc      => "sc",
+			ScEvo   => "sc_e",
+			ScKai   => "sc_k",
+			Tc      => "tc",
+			TcEvo   => "tc_e",
+			TcKai   => "tc_k",
+			Zero    => "zero",
+			ZeroEvo => "zero_e",
+			ZeroKai => "zero_k",
+			Ao      => "ao",
+			AoEvo   => "ao_e",
+			AoKai   => "ao_k",
+		}))
+	}
+}
@@ -90,6 +90,32 @@ pub fn bytecode(tokens: TokenStream0) -> TokenStream0 {
 		}
 	};
 
+	let opcode: Vec<_> = ctx.writes.iter().map(|WriteArm { span, games, ident, .. }| {
+		let games_name = games.iter().map(|a| &a.0).collect::<Vec<_>>();
+		let games_hex  = games.iter().map(|a| &a.1).collect::<Vec<_>>();
+		let name = ident.to_string();
+		q!{span=>
+			(__iset@(#(IS::#games_name)|*), #name) => {
+				Some(match __iset {
+					#(IS::#games_name => #games_hex,)*
+					#[allow(unreachable_patterns)]
+					_g => unsafe { std::hint::unreachable_unchecked() }
+				})
+			}
+		}
+	}).collect();
+	let opcode = q!{_=>
+		/// Returns the byte the instruction with the given name is encoded as, or `None` if it does
+		/// not exist in this game.
+		pub fn opcode(#func_args, __name: &str) -> Option<u8> {
+			type IS = #game_ty;
+			match (#game_expr, __name) {
+				#(#opcode)*
+				_ => None
+			}
+		}
+	};
+
 	let doc_insn_table = make_table(&ctx);
 
 	let Insn_body: Punctuated<_, Token![,]> = ctx.defs.iter().map(|Insn { span, attrs, ident, args, .. }| q!{span=>
@@ -116,6 +142,7 @@ pub fn bytecode(tokens: TokenStream0) -> TokenStream0 {
 		impl Insn {
 			#read
 			#write
+			#opcode
 		}
 	};
 
@@ -0,0 +1,179 @@
+//! Guessing what kind of data a file contains.
+//!
+//! Most files can be identified by their name alone, but some archives, such as `dat/`, have no
+//! names, so this also looks at the data itself.
+use cradle::ch;
+
+use crate::types::Game;
+use crate::scena;
+use crate::tables;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+	/// A scena file, readable by [`scena::ed6::Scena::read`] or [`scena::ed7::Scena::read`].
+	Scena,
+	/// A monster animation script, readable by [`themelios_ani::ed6::read_monster`].
+	Ani,
+	/// A DirectDraw Surface texture.
+	Dds,
+	/// A raw `._ch` image, with its pixel format, width, and height as guessed by [`ch::guess_from_byte_size`].
+	Ch(ch::Mode, usize, usize),
+	/// A `._ch` or `._cp` file that is not a known image; most likely a chip sheet or its pattern.
+	Chip,
+	/// One of the tables in [`tables`].
+	Table(Table),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+	Name,
+	Quest,
+	Town,
+	World,
+	Bgm,
+	Sound,
+	/// Map entrances, in `._en` files.
+	Ent,
+}
+
+/// Guesses what kind of data a file contains.
+///
+/// `name` is the file's name without any directory, such as `t0100._sn`, and `data` is its
+/// decompressed contents. Names are only trusted for files that are hard to tell apart by content;
+/// anything else is checked by trying to parse it.
+pub fn identify(game: Game, name: &str, data: &[u8]) -> Option<Kind> {
+	let name = name.to_lowercase();
+	let ext = name.rsplit_once('.').map_or("", |a| a.1);
+
+	if data.starts_with(b"DDS ") {
+		return Some(Kind::Dds)
+	}
+
+	if ext == "_ch" {
+		if let Some((mode, w, h)) = ch::guess_from_byte_size(&name, data.len()) {
+			return Some(Kind::Ch(mode, w, h))
+		}
+	}
+	if matches!(ext, "_ch" | "_cp") {
+		return Some(Kind::Chip)
+	}
+
+	let named_table = Table::from_name(&name);
+	if let Some(table) = named_table {
+		if is_table(game, table, data, false) {
+			return Some(Kind::Table(table))
+		}
+	}
+
+	let is_scena = if game.is_ed7() {
+		scena::ed7::Scena::read(game, data).is_ok()
+	} else {
+		scena::ed6::Scena::read(game, data).is_ok()
+	};
+	if is_scena {
+		return Some(Kind::Scena)
+	}
+
+	if !game.is_ed7() && themelios_ani::ed6::read_monster(game, data).is_ok() {
+		return Some(Kind::Ani)
+	}
+
+	// Files without a known name, such as in the `dat/` archives, could still be any table
+	if named_table.is_none() {
+		if let Some(table) = Table::ALL.into_iter().find(|t| is_table(game, *t, data, true)) {
+			return Some(Kind::Table(table))
+		}
+	}
+
+	None
+}
+
+/// Whether the data can be read as the given table. If `exact`, it also has to be written back
+/// identically, since some tables can be read from just about anything.
+fn is_table(game: Game, table: Table, data: &[u8], exact: bool) -> bool {
+	use themelios_common::util::{ReadError, WriteError};
+	use tables::*;
+	fn check<T>(
+		data: &[u8],
+		exact: bool,
+		read: impl FnOnce(&[u8]) -> Result<T, ReadError>,
+		write: impl FnOnce(&T) -> Result<Vec<u8>, WriteError>,
+	) -> bool {
+		match read(data) {
+			Ok(t) => !exact || write(&t).is_ok_and(|a| a == data),
+			Err(_) => false,
+		}
+	}
+	match (table, game.is_ed7()) {
+		(Table::Name,  false) => check(data, exact, |d| name::ED6Name::read(game, d), |(a, b)| name::ED6Name::write(game, a, b)),
+		(Table::Name,  true)  => check(data, exact, name::ED7Name::read, |a| name::ED7Name::write(a)),
+		(Table::Quest, false) => check(data, exact, quest::ED6Quest::read, |a| quest::ED6Quest::write(a)),
+		(Table::Quest, true)  => check(data, exact, quest::ED7Quest::read, |a| quest::ED7Quest::write(a)),
+		(Table::Town,  _)     => check(data, exact, |d| town::Town::read(game, d), |a| town::Town::write(game, a)),
+		(Table::World, false) => check(data, exact, world::ED6World::read, |a| world::ED6World::write(a)),
+		(Table::Bgm,   true)  => check(data, exact, bgm::ED7Bgm::read, |a| bgm::ED7Bgm::write(a)),
+		(Table::Sound, true)  => check(data, exact, se::ED7Sound::read, |a| se::ED7Sound::write(a)),
+		(Table::Ent,   false) => check(data, exact, ent::ED6Ent::read, |a| ent::ED6Ent::write(a)),
+		_ => false,
+	}
+}
+
+impl Table {
+	pub const ALL: [Table; 7] = [
+		Table::Name,
+		Table::Quest,
+		Table::Town,
+		Table::World,
+		Table::Bgm,
+		Table::Sound,
+		Table::Ent,
+	];
+
+	/// Guesses which table a file contains from its name alone, such as `t_name._dt`.
+	pub fn from_name(name: &str) -> Option<Table> {
+		let name = name.to_lowercase();
+		let (stem, ext) = name.split_once('.')?;
+		Some(match (stem, ext) {
+			("t_name",  "_dt") => Table::Name,
+			("t_quest", "_dt") => Table::Quest,
+			("t_town",  "_dt") => Table::Town,
+			("t_world", "_dt") => Table::World,
+			("t_bgm",   "_dt") => Table::Bgm,
+			("t_se",    "_dt") => Table::Sound,
+			(_,         "_en") => Table::Ent,
+			_ => return None,
+		})
+	}
+}
+
+impl std::fmt::Display for Kind {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Kind::Scena => write!(f, "scena"),
+			Kind::Ani => write!(f, "ani"),
+			Kind::Dds => write!(f, "dds"),
+			Kind::Ch(mode, w, h) => write!(f, "ch ({mode:?} {w}×{h})"),
+			Kind::Chip => write!(f, "chip"),
+			Kind::Table(t) => write!(f, "table ({t:?})"),
+		}
+	}
+}
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	let data = std::fs::read("../data/fc.extract/02/t_world._dt")?;
+	assert_eq!(identify(Game::Fc, "t_world._dt", &data), Some(Kind::Table(Table::World)));
+	Ok(())
+}
+
+#[test]
+fn test_unnamed() -> Result<(), Box<dyn std::error::Error>> {
+	use glam::IVec2;
+	use crate::types::FileId;
+	let data = tables::world::ED6World::write(&[
+		tables::world::ED6World { scena: FileId(0x00010000), pos: IVec2::new(100, -200) },
+	])?;
+	assert_eq!(identify(Game::Fc, "file[0x0003]", &data), Some(Kind::Table(Table::World)));
+	assert_eq!(identify(Game::Fc, "t_name._dt", &data), None);
+	Ok(())
+}
@@ -4,5 +4,6 @@ pub use themelios_common::types as types;
 pub use themelios_scena::text;
 pub mod scena;
 pub mod lookup;
+pub mod identify;
 
 pub use themelios_common::util::{ReadError, WriteError};
@@ -6,18 +6,11 @@ pub use themelios_archive::lookup::*;
 ///
 /// This should be all that's needed unless any mods add new files to the archives.
 pub fn default_for(game: crate::types::Game) -> &'static (dyn Lookup + Send + Sync) {
-	use std::io::Read;
-	use zstd::dict::DecoderDictionary as DD;
-
 	fn load(bytes: &[u8]) -> ED6Lookup {
-		let mut dec = zstd::Decoder::with_prepared_dictionary(bytes, &DICT).unwrap();
-		let mut data = Vec::new();
-		dec.read_to_end(&mut data).unwrap();
-		ED6Lookup::read_ed6i(&data).unwrap()
+		read_ed6i_zst(bytes).unwrap()
 	}
 
 	lazy_static::lazy_static! {
-		static ref DICT: DD<'static> = DD::copy(include_bytes!("../index/dict"));
 		pub static ref FC:     ED6Lookup = load(include_bytes!("../index/fc.ed6i.zst"));
 		pub static ref SC:     ED6Lookup = load(include_bytes!("../index/sc.ed6i.zst"));
 		pub static ref TC:     ED6Lookup = load(include_bytes!("../index/3rd.ed6i.zst"));
@@ -38,3 +31,91 @@ pub fn default_for(game: crate::types::Game) -> &'static (dyn Lookup + Send + Sy
 		Ao | AoEvo | AoKai => &ED7Lookup
 	}
 }
+
+#[cfg(feature = "indexes")]
+lazy_static::lazy_static! {
+	static ref DEC_DICT: zstd::dict::DecoderDictionary<'static> =
+		zstd::dict::DecoderDictionary::copy(include_bytes!("../index/dict"));
+	static ref ENC_DICT: zstd::dict::EncoderDictionary<'static> =
+		zstd::dict::EncoderDictionary::copy(include_bytes!("../index/dict"), 19);
+}
+
+#[cfg(feature = "indexes")]
+/// Reads a zstd-compressed .ed6i file, such as those used by [`default_for`].
+///
+/// These are compressed with a shared dictionary, so they cannot be read with a normal zstd decoder.
+pub fn read_ed6i_zst(data: &[u8]) -> std::io::Result<ED6Lookup> {
+	use std::io::Read;
+	let mut dec = zstd::Decoder::with_prepared_dictionary(data, &DEC_DICT)?;
+	let mut data = Vec::new();
+	dec.read_to_end(&mut data)?;
+	ED6Lookup::read_ed6i(&data)
+		.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(feature = "indexes")]
+/// Writes a zstd-compressed .ed6i file, readable by [`read_ed6i_zst`].
+pub fn write_ed6i_zst(lookup: &ED6Lookup) -> std::io::Result<Vec<u8>> {
+	use std::io::Write;
+	let ed6i = lookup.write_ed6i()
+		.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+	let mut out = Vec::new();
+	let mut enc = zstd::Encoder::with_prepared_dictionary(&mut out, &ENC_DICT)?;
+	enc.set_pledged_src_size(Some(ed6i.len() as u64))?;
+	enc.write_all(&ed6i)?;
+	enc.finish()?;
+	Ok(out)
+}
+
+/// Loads a lookup with [`load`], and layers the others on top with [`LayeredLookup::extend`].
+///
+/// This is useful for combining a game's lookup with a list of files added by mods.
+pub fn load_layered<P: AsRef<std::path::Path>>(base: P, layers: impl IntoIterator<Item=P>) -> std::io::Result<LayeredLookup> {
+	let load = |path: P| {
+		let path = path.as_ref();
+		load(path).map_err(|e| std::io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+	};
+	let mut lookup = LayeredLookup::new(load(base)?);
+	for path in layers {
+		lookup.extend(&load(path)?);
+	}
+	Ok(lookup)
+}
+
+/// Loads an [`ED6Lookup`] from a path of any of the supported kinds:
+///
+/// - A directory containing `ED6_DTxx.dir` files, loaded with [`ED6Lookup::for_pc`].
+/// - A directory containing a `scenario` directory, loaded with [`ED6Lookup::for_vita`].
+/// - A `.txt` file, loaded with [`ED6Lookup::read_txt`].
+/// - A `.zst` file, loaded with [`read_ed6i_zst`], if the `indexes` feature is enabled.
+/// - Any other file, loaded with [`ED6Lookup::read_ed6i`].
+pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<ED6Lookup> {
+	use std::io::{Error, ErrorKind};
+	let path = path.as_ref();
+	let ext = path.extension().and_then(|a| a.to_str());
+
+	if path.is_dir() {
+		for file in path.read_dir()? {
+			let name = file?.file_name();
+			let name = name.to_string_lossy();
+			if name.starts_with("ED6_DT") && name.ends_with(".dir") {
+				return ED6Lookup::for_pc(path)
+			}
+		}
+		if path.join("scenario").is_dir() {
+			return ED6Lookup::for_vita(path)
+		}
+		return Err(Error::new(ErrorKind::NotFound, format!("{} does not look like a game directory", path.display())))
+	}
+
+	let data = std::fs::read(path)?;
+	match ext {
+		Some("txt") => {
+			let text = String::from_utf8(data).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+			ED6Lookup::read_txt(&text)
+		}
+		#[cfg(feature = "indexes")]
+		Some("zst") => read_ed6i_zst(&data),
+		_ => ED6Lookup::read_ed6i(&data).map_err(|e| Error::new(ErrorKind::InvalidData, e)),
+	}
+}
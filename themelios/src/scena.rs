@@ -4,3 +4,4 @@ pub mod ed6;
 pub mod ed7;
 
 pub mod decompile;
+pub mod detect;
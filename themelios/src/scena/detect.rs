@@ -0,0 +1,148 @@
+//! Guessing which game a scena file belongs to.
+//!
+//! There is no indicator in the files themselves, and many files parse successfully as several
+//! games, so this parses it as every game and ranks them by how plausible the result looks.
+use crate::types::Game;
+use super::code::{Code, FlatInsn};
+use super::{ed6, ed7};
+
+/// Additional information that can help telling games apart.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hints<'a> {
+	/// The file's name, such as `t0100._sn` or `c0100.bin`.
+	pub filename: Option<&'a str>,
+	/// The number of the `ED6_DTxx` archive the file was taken from.
+	pub archive: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+	pub game: Game,
+	/// Higher is more plausible. The absolute value has no particular meaning.
+	pub score: i32,
+	/// Human-readable reasons for the score.
+	pub reasons: Vec<String>,
+}
+
+/// The games to try, in order of preference if there is a tie.
+pub const GAMES: [Game; 12] = [
+	Game::Fc, Game::Sc, Game::Tc, Game::ZeroKai, Game::AoKai, // Pc
+	Game::FcEvo, Game::ScEvo, Game::TcEvo, Game::ZeroEvo, Game::AoEvo, // Evo
+	Game::Zero, Game::Ao, // Geofront
+];
+
+/// Parses the file as each of the [`GAMES`], and returns the ones it could be parsed as, most
+/// plausible first.
+///
+/// Since strings are strictly decoded and unknown instructions are rejected while parsing, those
+/// are not scored separately. Instead, the score is based on:
+/// - whether writing the file back gives identical data, which will not happen if the function
+///   table was misinterpreted,
+/// - instructions whose purpose is unknown, which are more common in misparses,
+/// - instructions that only exist in the Evolution versions,
+/// - the hints, if any.
+pub fn detect(data: &[u8], hints: Hints) -> Vec<Candidate> {
+	let mut candidates = Vec::new();
+	for game in GAMES {
+		let mut c = Candidate { game, score: 0, reasons: Vec::new() };
+		let (roundtrip, functions) = if game.is_ed7() {
+			let Ok(scena) = ed7::Scena::read(game, data) else { continue };
+			(ed7::Scena::write(game, &scena).ok(), scena.functions)
+		} else {
+			let Ok(scena) = ed6::Scena::read(game, data) else { continue };
+			(ed6::Scena::write(game, &scena).ok(), scena.functions)
+		};
+
+		if roundtrip.as_deref() == Some(data) {
+			c.score += 100;
+			c.reasons.push("roundtrips exactly".to_owned());
+		}
+
+		let (unknown, evo) = count_insns(&functions);
+		if unknown > 0 {
+			c.score -= unknown as i32;
+			c.reasons.push(format!("{unknown} unknown instructions"));
+		}
+		if evo > 0 {
+			c.score += 10;
+			c.reasons.push(format!("{evo} Evolution-only instructions"));
+		}
+
+		apply_hints(&mut c, hints);
+		candidates.push(c);
+	}
+	// Stable sort, so ties are kept in preference order
+	candidates.sort_by_key(|c| -c.score);
+	candidates
+}
+
+/// Returns the candidates that share the top score. If there is more than one, the result of
+/// [`detect`] is ambiguous.
+pub fn ties(candidates: &[Candidate]) -> &[Candidate] {
+	let n = candidates.iter().take_while(|c| c.score == candidates[0].score).count();
+	&candidates[..n]
+}
+
+fn count_insns(functions: &[Code]) -> (usize, usize) {
+	let mut unknown = 0;
+	let mut evo = 0;
+	for insn in functions.iter().flat_map(|f| f.iter()) {
+		let FlatInsn::Insn(insn) = insn else { continue };
+		let name = insn.name();
+		// Instructions with unknown purpose are named after their opcode, like `Sc_7D`
+		if name.rsplit_once('_').is_some_and(|(_, op)| op.len() == 2 && op.chars().all(|c| c.is_ascii_hexdigit())) {
+			unknown += 1;
+		}
+		if name.contains("Evo") {
+			evo += 1;
+		}
+	}
+	(unknown, evo)
+}
+
+fn apply_hints(c: &mut Candidate, hints: Hints) {
+	let is_pc_ed6 = matches!(c.game, Game::Fc | Game::Sc | Game::Tc);
+	if let Some(filename) = hints.filename {
+		let filename = filename.to_lowercase();
+		// Only count matches, since an unusual name says nothing about the game
+		let matches = if is_pc_ed6 { filename.ends_with("._sn") } else { filename.ends_with(".bin") };
+		if matches {
+			c.score += 20;
+			c.reasons.push("matches file extension".to_owned());
+		}
+	}
+	if let Some(archive) = hints.archive {
+		let matches = match archive {
+			0x01 => c.game == Game::Fc,
+			0x21 => matches!(c.game, Game::Sc | Game::Tc),
+			_ => false,
+		};
+		if matches {
+			c.score += 20;
+			c.reasons.push(format!("matches archive ED6_DT{archive:02X}"));
+		}
+	}
+}
+
+#[test]
+fn test() -> Result<(), Box<dyn std::error::Error>> {
+	let data = std::fs::read("../data/fc.extract/01/t0100._sn")?;
+	let hints = Hints { filename: Some("t0100._sn"), archive: Some(0x01) };
+	let candidates = detect(&data, hints);
+	assert_eq!(ties(&candidates).len(), 1);
+	assert_eq!(candidates[0].game, Game::Fc);
+	Ok(())
+}
+
+#[test]
+fn test_hints() {
+	let score = |game, filename| {
+		let mut c = Candidate { game, score: 0, reasons: Vec::new() };
+		apply_hints(&mut c, Hints { filename: Some(filename), archive: None });
+		c.score
+	};
+	assert!(score(Game::Fc, "t0100._sn") > score(Game::FcEvo, "t0100._sn"));
+	assert!(score(Game::FcEvo, "t0100.bin") > score(Game::Fc, "t0100.bin"));
+	assert_eq!(score(Game::Fc, "-"), score(Game::FcEvo, "-"));
+	assert_eq!(score(Game::Fc, "-"), score(Game::Ao, "-"));
+}
@@ -16,10 +16,13 @@ impl ED6World {
 		let mut table = Vec::new();
 		loop {
 			let scena = FileId(f.u32()?);
-			let pos = IVec2 { x: f.i32()?, y: f.i32()? };
 			if scena == FileId(0xFFFFFFFF) {
+				// The terminator has no position, which `write` relies on
+				f.check_u32(0)?;
+				f.check_u32(0)?;
 				break
 			}
+			let pos = IVec2 { x: f.i32()?, y: f.i32()? };
 			table.push(ED6World { scena, pos });
 		}
 		Ok(table)
@@ -32,14 +35,22 @@ impl ED6World {
 			f.i32(a.pos.x);
 			f.i32(a.pos.y);
 		}
+		f.u32(0xFFFFFFFF);
+		f.i32(0);
+		f.i32(0);
 		Ok(f.finish()?)
 	}
 }
 
 #[test]
 fn test() -> Result<(), Box<dyn std::error::Error>> {
-	ED6World::read(&std::fs::read("../data/fc.extract/02/t_world._dt")?)?;
-	ED6World::read(&std::fs::read("../data/sc.extract/22/t_world._dt")?)?;
-	ED6World::read(&std::fs::read("../data/3rd.extract/22/t_world._dt")?)?;
+	for path in [
+		"../data/fc.extract/02/t_world._dt",
+		"../data/sc.extract/22/t_world._dt",
+		"../data/3rd.extract/22/t_world._dt",
+	] {
+		let data = std::fs::read(path)?;
+		assert!(ED6World::write(&ED6World::read(&data)?)? == data, "{path}");
+	}
 	Ok(())
 }